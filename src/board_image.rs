@@ -0,0 +1,253 @@
+use ab_glyph::{Font, FontRef, PxScale};
+use anyhow::anyhow;
+use pleco::{Board, PieceType, Player, SQ};
+use tiny_skia::{
+    Color, FillRule, Paint, PathBuilder, Pixmap, PremultipliedColorU8, Rect, Stroke, Transform,
+};
+
+use crate::{notation::destination, Error};
+
+/// DejaVu Sans, for its chess glyphs and the coordinates. See `assets/fonts/LICENSE`.
+const FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+
+const SQUARE_SIZE: u32 = 64;
+const BOARD_SIZE: u32 = SQUARE_SIZE * 8;
+
+const LIGHT_SQUARE: [u8; 3] = [240, 217, 181];
+const DARK_SQUARE: [u8; 3] = [181, 136, 99];
+const LAST_MOVE: [u8; 3] = [205, 210, 106];
+const CHECK: [u8; 3] = [235, 97, 80];
+const WHITE_PIECE: [u8; 3] = [255, 255, 255];
+const BLACK_PIECE: [u8; 3] = [20, 20, 20];
+
+/// How the pieces are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, poise::ChoiceParameter)]
+pub enum PieceSet {
+    /// The chess symbols of DejaVu Sans.
+    #[default]
+    #[name = "Classic"]
+    Classic,
+    /// Discs marked with the piece's letter, which stay readable on small screens.
+    #[name = "Letters"]
+    Letters,
+}
+
+/// Which side of the board is drawn at the bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Orientation {
+    #[name = "White at the bottom"]
+    White,
+    #[name = "Black at the bottom"]
+    Black,
+}
+
+impl From<Orientation> for Player {
+    fn from(orientation: Orientation) -> Self {
+        match orientation {
+            Orientation::White => Player::White,
+            Orientation::Black => Player::Black,
+        }
+    }
+}
+
+/// Draws `board` as a PNG, with `orientation` at the bottom, coordinates along the edges, the last move
+/// highlighted and the king marked when it is in check.
+pub fn render_board(
+    board: &Board,
+    orientation: Player,
+    piece_set: PieceSet,
+) -> Result<Vec<u8>, Error> {
+    let font = FontRef::try_from_slice(FONT)?;
+    let mut pixmap = Pixmap::new(BOARD_SIZE, BOARD_SIZE)
+        .ok_or_else(|| anyhow!("Unable to allocate the board image."))?;
+
+    let last_move = board.last_move();
+    let checked_king = board.in_check().then(|| board.king_sq(board.turn()));
+
+    for square in (0..64).map(SQ) {
+        let (x, y) = square_origin(square, orientation);
+        let is_light = (square.file_idx_of_sq() + square.rank_idx_of_sq()) % 2 == 1;
+
+        let mut colour = if is_light { LIGHT_SQUARE } else { DARK_SQUARE };
+        let in_last_move = match last_move {
+            Some(last_move) => last_move.get_src() == square || destination(last_move) == square,
+            None => false,
+        };
+        if in_last_move {
+            colour = mix(colour, LAST_MOVE, 0.6);
+        }
+        if checked_king == Some(square) {
+            colour = mix(colour, CHECK, 0.8);
+        }
+        fill_square(&mut pixmap, x, y, colour);
+
+        // Coordinates go in the corners of the squares along the bottom and left edges, in the other square colour.
+        let text_colour = if is_light { DARK_SQUARE } else { LIGHT_SQUARE };
+        let (column, row) = (x / SQUARE_SIZE as f32, y / SQUARE_SIZE as f32);
+        if row == 7.0 {
+            let file = (b'a' + square.file_idx_of_sq()) as char;
+            draw_char(
+                &mut pixmap,
+                &font,
+                file,
+                14.0,
+                (x + SQUARE_SIZE as f32 - 8.0, y + SQUARE_SIZE as f32 - 9.0),
+                text_colour,
+            );
+        }
+        if column == 0.0 {
+            let rank = (b'1' + square.rank_idx_of_sq()) as char;
+            draw_char(
+                &mut pixmap,
+                &font,
+                rank,
+                14.0,
+                (x + 7.0, y + 9.0),
+                text_colour,
+            );
+        }
+
+        let piece = board.piece_at_sq(square);
+        if let Some(player) = piece.player() {
+            draw_piece(
+                &mut pixmap,
+                &font,
+                piece_set,
+                piece.type_of(),
+                player,
+                (x, y),
+            );
+        }
+    }
+
+    Ok(pixmap.encode_png()?)
+}
+
+/// The top left corner of `square` in the image.
+fn square_origin(square: SQ, orientation: Player) -> (f32, f32) {
+    let file = u32::from(square.file_idx_of_sq());
+    let rank = u32::from(square.rank_idx_of_sq());
+    let (column, row) = match orientation {
+        Player::White => (file, 7 - rank),
+        Player::Black => (7 - file, rank),
+    };
+
+    ((column * SQUARE_SIZE) as f32, (row * SQUARE_SIZE) as f32)
+}
+
+fn draw_piece(
+    pixmap: &mut Pixmap,
+    font: &FontRef,
+    piece_set: PieceSet,
+    piece: PieceType,
+    player: Player,
+    (x, y): (f32, f32),
+) {
+    let centre = (x + SQUARE_SIZE as f32 / 2.0, y + SQUARE_SIZE as f32 / 2.0);
+    let (fill, ink) = match player {
+        Player::White => (WHITE_PIECE, BLACK_PIECE),
+        Player::Black => (BLACK_PIECE, WHITE_PIECE),
+    };
+
+    match piece_set {
+        PieceSet::Classic => {
+            // The solid symbol is filled in the piece's colour, then the outlined one is drawn over it in black.
+            let (outlined, solid) = chess_symbols(piece);
+            draw_char(pixmap, font, solid, 56.0, centre, fill);
+            draw_char(pixmap, font, outlined, 56.0, centre, BLACK_PIECE);
+        }
+        PieceSet::Letters => {
+            if let Some(disc) = PathBuilder::from_circle(centre.0, centre.1, 24.0) {
+                let mut paint = Paint::default();
+                paint.set_color(to_colour(fill));
+                pixmap.fill_path(
+                    &disc,
+                    &paint,
+                    FillRule::Winding,
+                    Transform::identity(),
+                    None,
+                );
+
+                paint.set_color(to_colour(BLACK_PIECE));
+                let stroke = Stroke {
+                    width: 2.0,
+                    ..Stroke::default()
+                };
+                pixmap.stroke_path(&disc, &paint, &stroke, Transform::identity(), None);
+            }
+            draw_char(pixmap, font, piece.char_upper(), 30.0, centre, ink);
+        }
+    }
+}
+
+/// The outlined and the solid Unicode symbol of a piece.
+fn chess_symbols(piece: PieceType) -> (char, char) {
+    match piece {
+        PieceType::K => ('♔', '♚'),
+        PieceType::Q => ('♕', '♛'),
+        PieceType::R => ('♖', '♜'),
+        PieceType::B => ('♗', '♝'),
+        PieceType::N => ('♘', '♞'),
+        _ => ('♙', '♟'),
+    }
+}
+
+/// Draws `c` centred on `centre`.
+fn draw_char(
+    pixmap: &mut Pixmap,
+    font: &FontRef,
+    c: char,
+    size: f32,
+    centre: (f32, f32),
+    colour: [u8; 3],
+) {
+    let glyph = font.glyph_id(c).with_scale(PxScale::from(size));
+    let Some(outline) = font.outline_glyph(glyph) else {
+        return;
+    };
+
+    let bounds = outline.px_bounds();
+    let left = (centre.0 - bounds.width() / 2.0).round() as i32;
+    let top = (centre.1 - bounds.height() / 2.0).round() as i32;
+
+    outline.draw(|x, y, coverage| {
+        blend(pixmap, left + x as i32, top + y as i32, colour, coverage);
+    });
+}
+
+fn fill_square(pixmap: &mut Pixmap, x: f32, y: f32, colour: [u8; 3]) {
+    let Some(rect) = Rect::from_xywh(x, y, SQUARE_SIZE as f32, SQUARE_SIZE as f32) else {
+        return;
+    };
+
+    let mut paint = Paint::default();
+    paint.set_color(to_colour(colour));
+    pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+}
+
+/// Paints `colour` over an opaque pixel, `coverage` being how much of the pixel it covers.
+fn blend(pixmap: &mut Pixmap, x: i32, y: i32, colour: [u8; 3], coverage: f32) {
+    let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
+    if x < 0 || y < 0 || x >= width || y >= height {
+        return;
+    }
+
+    let pixel = &mut pixmap.pixels_mut()[(y * width + x) as usize];
+    let below = [pixel.red(), pixel.green(), pixel.blue()];
+    let [red, green, blue] = mix(below, colour, coverage.clamp(0.0, 1.0));
+    if let Some(blended) = PremultipliedColorU8::from_rgba(red, green, blue, 255) {
+        *pixel = blended;
+    }
+}
+
+fn mix(below: [u8; 3], above: [u8; 3], amount: f32) -> [u8; 3] {
+    let channel = |i: usize| {
+        (f32::from(below[i]) * (1.0 - amount) + f32::from(above[i]) * amount).round() as u8
+    };
+
+    [channel(0), channel(1), channel(2)]
+}
+
+fn to_colour([red, green, blue]: [u8; 3]) -> Color {
+    Color::from_rgba8(red, green, blue, 255)
+}
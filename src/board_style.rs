@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use pleco::{Board, Player};
+use poise::serenity_prelude::{GuildId, Http};
+
+use crate::{
+    board_image::{render_board, PieceSet},
+    board_text::{render_text_board, BoardGlyphs},
+    Error,
+};
+
+/// How a guild's boards are shown, as chosen with `/board_style`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, poise::ChoiceParameter)]
+pub enum BoardStyle {
+    #[default]
+    #[name = "Image"]
+    Image,
+    /// For servers that don't allow the bot to post images.
+    #[name = "Unicode text"]
+    Unicode,
+    /// The guild's own piece emoji, named `wk`, `bq` and so on.
+    #[name = "Server emoji"]
+    Emoji,
+}
+
+/// A board, ready to be posted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoardContent {
+    /// A PNG, sent as an attachment.
+    Image(Vec<u8>),
+    /// Message text.
+    Text(String),
+}
+
+/// Everything needed to draw boards in a guild's style.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoardLook {
+    Image(PieceSet),
+    Text(Box<BoardGlyphs>),
+}
+
+impl BoardLook {
+    /// Looks up what `style` needs in `guild_id`. For emoji, that is the guild's custom emoji. If they can't be
+    /// fetched, the Unicode symbols are used in their place.
+    pub async fn for_guild(http: &Http, guild_id: GuildId, style: BoardStyle) -> Self {
+        match style {
+            BoardStyle::Image => BoardLook::Image(PieceSet::default()),
+            BoardStyle::Unicode => BoardLook::Text(Box::new(BoardGlyphs::unicode())),
+            BoardStyle::Emoji => {
+                let guild_emoji = match guild_id.emojis(http).await {
+                    Ok(emojis) => emojis
+                        .into_iter()
+                        .map(|emoji| (emoji.name.clone(), emoji.to_string()))
+                        .collect(),
+                    Err(error) => {
+                        tracing::warn!(
+                            "Unable to fetch the emoji of guild {}: {}",
+                            guild_id,
+                            error
+                        );
+                        HashMap::new()
+                    }
+                };
+
+                BoardLook::Text(Box::new(BoardGlyphs::emoji(&guild_emoji)))
+            }
+        }
+    }
+
+    /// Draws `board` with `orientation` at the bottom.
+    pub fn draw(&self, board: &Board, orientation: Player) -> Result<BoardContent, Error> {
+        Ok(match self {
+            BoardLook::Image(piece_set) => {
+                BoardContent::Image(render_board(board, orientation, *piece_set)?)
+            }
+            BoardLook::Text(glyphs) => {
+                BoardContent::Text(render_text_board(board, orientation, glyphs))
+            }
+        })
+    }
+}
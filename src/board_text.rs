@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use pleco::{Board, PieceType, Player, SQ};
+
+/// The piece types in the order `BoardGlyphs::pieces` stores them.
+const PIECE_ORDER: [PieceType; 6] = [
+    PieceType::P,
+    PieceType::N,
+    PieceType::B,
+    PieceType::R,
+    PieceType::Q,
+    PieceType::K,
+];
+
+/// What a text board is drawn with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoardGlyphs {
+    /// White's pieces, then Black's, each in `PIECE_ORDER`.
+    pieces: [[String; 6]; 2],
+    light_square: String,
+    dark_square: String,
+    /// The labels of the a to h files and the 1st to 8th ranks.
+    files: [String; 8],
+    ranks: [String; 8],
+    /// Fills the corner left of the file labels.
+    corner: String,
+    /// Separates the files along the bottom edge.
+    file_separator: String,
+    /// Whether the board is sent as a code block, which keeps the glyphs lined up.
+    code_block: bool,
+}
+
+impl BoardGlyphs {
+    /// The Unicode chess symbols in a code block. Works everywhere, no setup needed.
+    pub fn unicode() -> Self {
+        let pieces = |symbols: [char; 6]| symbols.map(String::from);
+
+        Self {
+            pieces: [
+                pieces(['♙', '♘', '♗', '♖', '♕', '♔']),
+                pieces(['♟', '♞', '♝', '♜', '♛', '♚']),
+            ],
+            light_square: "·".to_string(),
+            dark_square: "·".to_string(),
+            files: ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'].map(String::from),
+            ranks: ['1', '2', '3', '4', '5', '6', '7', '8'].map(String::from),
+            corner: " ".to_string(),
+            file_separator: " ".to_string(),
+            code_block: true,
+        }
+    }
+
+    /// Coloured squares with the guild's own piece emoji on them. `guild_emoji` maps emoji names to how they are
+    /// written in a message, e.g. `wk` to `<:wk:1234>`.
+    ///
+    /// Pieces are looked up as `wp`, `wn`, `wb`, `wr`, `wq` and `wk` for White and `bp` to `bk` for Black. Any the
+    /// guild doesn't have fall back to the Unicode symbols.
+    pub fn emoji(guild_emoji: &HashMap<String, String>) -> Self {
+        let mut pieces = Self::unicode().pieces;
+        for (symbols, prefix) in pieces.iter_mut().zip(['w', 'b']) {
+            for (symbol, piece) in symbols.iter_mut().zip(PIECE_ORDER) {
+                if let Some(emoji) = guild_emoji.get(&format!("{}{}", prefix, piece.char_lower())) {
+                    *symbol = emoji.clone();
+                }
+            }
+        }
+
+        Self {
+            pieces,
+            light_square: "🟨".to_string(),
+            dark_square: "🟫".to_string(),
+            // Regional indicators next to each other would be shown as flags.
+            files: ['🇦', '🇧', '🇨', '🇩', '🇪', '🇫', '🇬', '🇭'].map(String::from),
+            ranks: ['1', '2', '3', '4', '5', '6', '7', '8']
+                .map(|rank| format!("{}\u{fe0f}\u{20e3}", rank)),
+            corner: "⬛".to_string(),
+            file_separator: "\u{200b}".to_string(),
+            code_block: false,
+        }
+    }
+
+    fn piece(&self, player: Player, piece: PieceType) -> &str {
+        let index = PIECE_ORDER
+            .iter()
+            .position(|kind| *kind == piece)
+            .unwrap_or(0);
+
+        &self.pieces[player as usize][index]
+    }
+}
+
+/// Draws `board` as text, with `orientation` at the bottom and coordinates along the left and bottom edges.
+pub fn render_text_board(board: &Board, orientation: Player, glyphs: &BoardGlyphs) -> String {
+    let (ranks, files): (Vec<u8>, Vec<u8>) = match orientation {
+        Player::White => ((0..8).rev().collect(), (0..8).collect()),
+        Player::Black => ((0..8).collect(), (0..8).rev().collect()),
+    };
+    let separator = if glyphs.code_block { " " } else { "" };
+
+    let mut lines: Vec<String> = ranks
+        .iter()
+        .map(|rank| {
+            let squares = files.iter().map(|file| {
+                let square = SQ(rank * 8 + file);
+                let piece = board.piece_at_sq(square);
+                match piece.player() {
+                    Some(player) => glyphs.piece(player, piece.type_of()),
+                    None if (rank + file) % 2 == 1 => &glyphs.light_square,
+                    None => &glyphs.dark_square,
+                }
+            });
+
+            std::iter::once(glyphs.ranks[*rank as usize].as_str())
+                .chain(squares)
+                .collect::<Vec<&str>>()
+                .join(separator)
+        })
+        .collect();
+
+    let file_labels: Vec<&str> = files
+        .iter()
+        .map(|file| glyphs.files[*file as usize].as_str())
+        .collect();
+    lines.push(format!(
+        "{}{}{}",
+        glyphs.corner,
+        separator,
+        file_labels.join(&glyphs.file_separator)
+    ));
+
+    if glyphs.code_block {
+        format!("```\n{}\n```", lines.join("\n"))
+    } else {
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,46 @@
+use pleco::{
+    bot_prelude::{AlphaBetaSearcher, IterativeSearcher, JamboreeSearcher, Searcher},
+    Board,
+};
+use tokio::task::JoinHandle;
+
+/// The built-in opponents of `/play_bot`, each one of pleco's searchers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum BotLevel {
+    /// Iterative deepening, three plies deep. pleco's version of it overlooks mates, which makes it the weakest.
+    #[name = "Easy"]
+    Easy,
+    /// Alpha-beta, searching three plies deep.
+    #[name = "Medium"]
+    Medium,
+    /// Jamboree, a parallel alpha-beta, searching four plies deep.
+    #[name = "Hard"]
+    Hard,
+}
+
+impl BotLevel {
+    /// The bot's reply in `board`, in UCI, or `None` if it has no legal moves.
+    /// Searching can take seconds, so this belongs on a blocking thread. See `think`.
+    pub fn choose_move(self, board: Board) -> Option<String> {
+        let legal_moves = board.generate_moves();
+        let searched = match self {
+            BotLevel::Easy => IterativeSearcher::best_move(board, 3),
+            BotLevel::Medium => AlphaBetaSearcher::best_move(board, 3),
+            BotLevel::Hard => JamboreeSearcher::best_move(board, 4),
+        };
+
+        // The searchers give back a null move when every line looks equally lost.
+        let chosen = if legal_moves.contains(&searched) {
+            searched
+        } else {
+            *legal_moves.first()?
+        };
+
+        Some(chosen.stringify())
+    }
+}
+
+/// Starts searching for the bot's reply on a blocking thread, so the async runtime carries on meanwhile.
+pub fn think(level: BotLevel, board: Board) -> JoinHandle<Option<String>> {
+    tokio::task::spawn_blocking(move || level.choose_move(board))
+}
@@ -1,491 +1,632 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use anyhow::anyhow;
-use pleco::{Board, Player};
-use poise::serenity_prelude::{CacheHttp, GuildChannel, Member, Message, UserId};
-use tokio::{task::JoinHandle, time::interval};
-
-use crate::{Context, DiscordCommand, DiscordCommunication, Error};
-
-#[derive(Debug, Clone, Copy, buildstructor::Builder)]
+use pleco::Board;
+use poise::serenity_prelude::{GuildChannel, GuildId, Http, Message, UserId};
+use tokio::{
+    sync::mpsc,
+    task::JoinHandle,
+    time::{interval_at, sleep_until, Instant, MissedTickBehavior},
+};
+
+use crate::{
+    board_style::BoardLook,
+    bot::think,
+    database::{MatchProgress, MatchRecord},
+    engine::{
+        get_opposite_colour, ChessPlayer, GameState, MatchEngine, MatchEvent, PlayerSlot, Seat,
+    },
+    frontend::{is_gone_from_discord, DiscordFrontend, MatchFrontend},
+    match_registry::{MatchId, MatchInput, Reservation},
+    notation::{is_readable_move, read_fen},
+    Data, DiscordCommand, Error,
+};
+
+/// How long a finished match stays visible before its frontend is torn down.
+pub const THREAD_DELETION_DELAY: Duration = Duration::from_secs(30);
+
+/// How often the running clock is stored between moves. After a restart, the player to move gets back at most this
+/// much of the time they had spent.
+pub const CLOCK_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Runs a single match: every command sent to it goes through its `MatchEngine`,
+/// and the resulting events are stored and rendered by its `MatchFrontend`.
 pub struct ChessMatch {
-    state: GameState,
-    opponent_join_deadline: i32,
-    player_one: ChessPlayer,
-    player_two: ChessPlayer,
-    player_time: PlayerTime,
+    match_id: MatchId,
+    engine: MatchEngine,
+    /// A move sent by the player waiting for their opponent, to be played as soon as the opponent has moved.
+    premove: Option<Premove>,
+    /// The search for a bot's next move, while one is running.
+    bot_reply: Option<BotReply>,
+    frontend: Box<dyn MatchFrontend>,
+    data: Data,
+    match_rx: mpsc::Receiver<MatchInput>,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum GameState {
-    WaitingForOpponent,
-    Playing,
-    GameOver,
+#[derive(Debug, Clone)]
+struct Premove {
+    user_id: UserId,
+    chess_move: String,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, buildstructor::Builder)]
-pub struct ChessPlayer {
-    pub user_id: Option<UserId>,
-    player_slot: PlayerSlot,
-    in_game_representation: Player,
+#[derive(Debug)]
+struct BotReply {
+    /// The zobrist hash of the position the bot is searching. The reply is thrown away if a takeback changed it.
+    position: u64,
+    search: JoinHandle<Option<String>>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum PlayerSlot {
-    Player1,
-    Player2,
+/// What a match played in Discord needs from the bot.
+/// Unlike a command's `Context`, it isn't tied to a single command invocation, so restored matches can use it too.
+#[derive(Clone)]
+pub struct MatchContext {
+    http: Arc<Http>,
+    data: Data,
 }
 
-#[derive(Debug, Clone, Copy, buildstructor::Builder)]
-pub struct PlayerTime {
-    white: i32,
-    black: i32,
-}
+impl MatchContext {
+    pub fn new(http: Arc<Http>, data: Data) -> Self {
+        Self { http, data }
+    }
 
-#[derive(Debug, Clone, buildstructor::Builder)]
-struct MatchFrontend {
-    match_thread_message: Message,
-    match_thread: GuildChannel,
-    board_message: Message,
-    time_info_message: Message,
-    legal_move_message: Message,
+    /// How boards are drawn in `guild_id`, as chosen with `/board_style`.
+    async fn board_look(&self, guild_id: GuildId) -> Result<BoardLook, Error> {
+        let style = self.data.database.board_style(guild_id)?;
+
+        Ok(BoardLook::for_guild(&self.http, guild_id, style).await)
+    }
 }
 
 impl ChessMatch {
-    pub async fn spawn(
-        mut self,
-        ctx: Context<'_>,
+    /// Wraps an already registered match. Nothing happens until `run` is awaited.
+    fn new(
+        match_id: MatchId,
+        engine: MatchEngine,
+        frontend: Box<dyn MatchFrontend>,
+        data: Data,
+        match_rx: mpsc::Receiver<MatchInput>,
+    ) -> Self {
+        Self {
+            match_id,
+            engine,
+            premove: None,
+            bot_reply: None,
+            frontend,
+            data,
+            match_rx,
+        }
+    }
+
+    /// Registers a new match played through any frontend. Nothing happens until `run` is awaited, except that the
+    /// clock of a match with nobody to wait for, like one against a bot, starts now that it can be shown.
+    pub fn open(
+        match_id: MatchId,
+        guild_id: GuildId,
+        mut engine: MatchEngine,
+        frontend: Box<dyn MatchFrontend>,
+        data: Data,
+    ) -> Result<Self, Error> {
+        let player_one_id = engine
+            .player_one()
+            .user_id()
+            .ok_or_else(|| anyhow!("Player 1 has no user id."))?;
+        let match_rx = data.match_registry.register(
+            match_id,
+            guild_id,
+            &[player_one_id],
+            member_seats(&engine),
+        )?;
+
+        if engine.state() == GameState::Playing {
+            engine.start_clock(Instant::now());
+        }
+
+        Ok(ChessMatch::new(match_id, engine, frontend, data, match_rx))
+    }
+
+    /// Sets a new match up in a Discord thread: pins its messages, stores it and registers its players, who stop
+    /// being reserved once this returns. If any of that fails, the thread is deleted again.
+    /// Nothing is played until `run` is awaited.
+    pub async fn start(
+        engine: MatchEngine,
+        ctx: MatchContext,
         match_thread_message: Message,
         match_thread: GuildChannel,
-        player_1_member: Box<Member>,
-    ) -> Result<(), Error> {
-        let board = Board::start_pos();
-
-        let board_message = match_thread.say(ctx.http(), board.pretty_string()).await?;
-
-        let time_info_message = match_thread
-            .say(
-                ctx.http(),
-                format!(
-                    "Deadline for an opponent to join: {}",
-                    self.opponent_join_deadline
-                ),
-            )
-            .await?;
+        _reservation: Reservation,
+    ) -> Result<Self, Error> {
+        let started = ChessMatch::set_up(
+            engine,
+            &ctx,
+            match_thread_message.clone(),
+            match_thread.clone(),
+        )
+        .await;
 
-        let legal_move_message = match_thread
-            .say(
-                ctx.http(),
-                "White's legal moves in the current position: _".to_string(),
-            )
-            .await?;
-
-        legal_move_message.pin(ctx.http()).await?;
-        time_info_message.pin(ctx.http()).await?;
-        board_message.pin(ctx.http()).await?;
-
-        let mut frontend = MatchFrontend::builder()
-            .match_thread_message(match_thread_message)
-            .match_thread(match_thread)
-            .board_message(board_message)
-            .time_info_message(time_info_message)
-            .legal_move_message(legal_move_message)
-            .build();
-
-        let guild_id = player_1_member.guild_id;
-        let time_ticker_tx = ctx.data().system_communication_channel.0.clone();
-        let mut time_ticker_interval = interval(Duration::from_secs(1));
-        let time_ticker_task = tokio::spawn(async move {
-            loop {
-                if time_ticker_tx
-                    .send(DiscordCommunication(guild_id, DiscordCommand::TimeTick))
-                    .is_ok()
-                {
-                    time_ticker_interval.tick().await;
-                }
+        if started.is_err() {
+            discard_match_thread(&ctx.http, &match_thread_message, &match_thread).await;
+        }
+
+        started
+    }
+
+    async fn set_up(
+        engine: MatchEngine,
+        ctx: &MatchContext,
+        match_thread_message: Message,
+        match_thread: GuildChannel,
+    ) -> Result<Self, Error> {
+        let board_look = ctx.board_look(match_thread.guild_id).await?;
+        let frontend = DiscordFrontend::create(
+            ctx.http.clone(),
+            match_thread_message,
+            match_thread,
+            &engine,
+            board_look,
+        )
+        .await?;
+
+        let match_id = frontend.match_thread().id;
+        let guild_id = frontend.match_thread().guild_id;
+        let record = get_record(match_id, &frontend, get_progress(&engine, Instant::now())?);
+
+        ctx.data.database.save_match(&record)?;
+        let opened = ChessMatch::open(
+            match_id,
+            guild_id,
+            engine,
+            Box::new(frontend),
+            ctx.data.clone(),
+        );
+        if opened.is_err() {
+            if let Err(error) = ctx.data.database.delete_match(match_id) {
+                tracing::error!("Unable to delete match {}: {}", match_id, error);
             }
-        });
+        }
 
-        let mut system_communication_rx = ctx.data().system_communication_channel.0.subscribe();
-        while let Ok(communication) = system_communication_rx.recv().await {
-            if communication.0 == player_1_member.guild_id {
-                match communication.1 {
-                    DiscordCommand::JoinMatch(waiting_member, joining_member) => {
-                        if player_1_member.user.id == waiting_member.user.id {
-                            frontend
-                                .match_thread
-                                .say(
-                                    ctx.http(),
-                                    format!("{} just joined", joining_member.user.name),
-                                )
-                                .await?;
-
-                            self.player_two.user_id = Some(joining_member.user.id);
-                            self.player_turns(
-                                time_ticker_task,
-                                ctx,
-                                board,
-                                player_1_member,
-                                joining_member,
-                                frontend,
-                            )
-                            .await?;
-
-                            break;
-                        }
-                    }
-                    DiscordCommand::VerifyIfAlreadyInMatch(member, respond_tx) => {
-                        if member.user.id == player_1_member.user.id {
-                            respond_tx.send(true).await?;
-                        }
-                    }
-                    DiscordCommand::TimeTick => {
-                        self.opponent_join_deadline -= 1;
-                        frontend
-                            .time_info_message
-                            .edit(ctx.http(), |m| {
-                                m.content(format!(
-                                    "Deadline for an opponent to join: {}",
-                                    self.opponent_join_deadline
-                                ))
-                            })
-                            .await?;
-
-                        if self.opponent_join_deadline == 0 {
-                            time_ticker_task.abort();
-                            self.end_the_game(ctx, frontend).await?;
-
-                            break;
-                        }
-                    }
-                    _ => (),
+        opened
+    }
+
+    /// Picks a stored match back up after a restart, reusing its existing thread and pinned messages.
+    pub async fn restore(record: MatchRecord, ctx: MatchContext) -> Result<(), Error> {
+        let board_look = ctx.board_look(record.guild_id).await?;
+        let frontend = DiscordFrontend::reconnect(ctx.http, &record, board_look).await?;
+
+        ChessMatch::reopen(&record, Box::new(frontend), ctx.data)
+            .await?
+            .run()
+            .await
+    }
+
+    /// Rebuilds a stored match played through any frontend and registers its players again.
+    /// Nothing happens until `run` is awaited.
+    pub async fn reopen(
+        record: &MatchRecord,
+        mut frontend: Box<dyn MatchFrontend>,
+        data: Data,
+    ) -> Result<Self, Error> {
+        let engine = engine_from_progress(&record.progress, Instant::now())?;
+
+        let player_ids = [
+            Some(record.progress.player_one_id),
+            record.progress.player_two.user_id(),
+        ];
+        let match_rx = data.match_registry.register(
+            record.match_id,
+            record.guild_id,
+            &player_ids.into_iter().flatten().collect::<Vec<UserId>>(),
+            member_seats(&engine),
+        )?;
+
+        frontend.resume(&engine).await?;
+
+        Ok(ChessMatch::new(
+            record.match_id,
+            engine,
+            frontend,
+            data,
+            match_rx,
+        ))
+    }
+
+    /// Restores every match in the database, each on its own task.
+    /// Matches whose thread or pinned messages were deleted are dropped from the database. Any other failure, like
+    /// Discord being unreachable, leaves the match stored for the next start.
+    pub fn restore_all(ctx: MatchContext) -> Result<(), Error> {
+        for record in ctx.data.database.load_matches()? {
+            let ctx = ctx.clone();
+            tokio::spawn(async move {
+                let match_id = record.match_id;
+                let Err(error) = ChessMatch::restore(record, ctx.clone()).await else {
+                    return;
+                };
+                ctx.data.match_registry.deregister(match_id);
+
+                if !is_gone_from_discord(&error) {
+                    tracing::error!(
+                        "Match {} stopped after being restored, and is kept for the next start: {}",
+                        match_id,
+                        error
+                    );
+                    return;
                 }
-            }
+
+                tracing::warn!(
+                    "Match {} is gone from Discord, so it is dropped: {}",
+                    match_id,
+                    error
+                );
+                if let Err(error) = ctx.data.database.delete_match(match_id) {
+                    tracing::error!("Unable to delete match {}: {}", match_id, error);
+                }
+            });
         }
 
         Ok(())
     }
 
-    async fn player_turns(
-        mut self,
-        time_ticker_task: JoinHandle<()>,
-        ctx: Context<'_>,
-        mut board: Board,
-        player_1_member: Box<Member>,
-        player_2_member: Box<Member>,
-        mut frontend: MatchFrontend,
-    ) -> Result<(), Error> {
-        self.state = GameState::Playing;
-        let mut acting_player_colour = Player::White;
-
-        frontend
-            .match_thread
-            .say(ctx.http(), "The match has now started!")
-            .await?;
-
-        frontend
-            .time_info_message
-            .edit(ctx.http(), |m| {
-                m.content(format!(
-                    "{} ({}) Time: {}\n{} ({}) Time: {}",
-                    player_1_member.user.name,
-                    self.player_one.in_game_representation,
-                    self.get_colour_timeleft(self.player_one.in_game_representation),
-                    player_2_member.user.name,
-                    self.player_two.in_game_representation,
-                    self.get_colour_timeleft(self.player_two.in_game_representation),
-                ))
-            })
-            .await?;
-
-        frontend
-            .legal_move_message
-            .edit(ctx.http(), |m| {
-                m.content(format!(
-                    "{}'s legal moves in the current position:\n{:?}",
-                    acting_player_colour,
-                    board
-                        .generate_moves()
-                        .iter_mut()
-                        .map(|chess_move| chess_move.stringify())
-                        .collect::<Vec<String>>()
-                ))
-            })
-            .await?;
-
-        let mut system_communication_rx = ctx.data().system_communication_channel.0.subscribe();
-        while let Ok(communication) = system_communication_rx.recv().await {
-            if communication.0 == player_1_member.guild_id {
-                match communication.1 {
-                    DiscordCommand::JoinMatch(_, _) => todo!(),
-                    DiscordCommand::MakeMove(query_member, chess_move) => {
-                        if let Ok(query_player) = self.check_if_member_is_in_game(
-                            query_member,
-                            &player_1_member,
-                            &player_2_member,
-                        ) {
-                            if query_player
-                                == self.get_acting_chessplayer(acting_player_colour).unwrap()
-                            {
-                                let legal_moves = board.generate_moves();
-
-                                let stringified_legal_moves = board
-                                    .generate_moves()
-                                    .iter_mut()
-                                    .map(|chess_move| chess_move.stringify())
-                                    .collect::<Vec<String>>();
-
-                                if stringified_legal_moves.contains(&chess_move) {
-                                    if let Some(bit_move) = legal_moves.get(
-                                        stringified_legal_moves
-                                            .iter()
-                                            .position(|m| m == &chess_move)
-                                            .unwrap(),
-                                    ) {
-                                        board.apply_move(*bit_move);
-
-                                        frontend
-                                            .board_message
-                                            .edit(ctx.http(), |m| m.content(board.pretty_string()))
-                                            .await?;
-
-                                        frontend
-                                            .match_thread
-                                            .say(
-                                                ctx.http(),
-                                                format!(
-                                                    "{} ({}) made the move {}.",
-                                                    get_member_from_chessplayer(
-                                                        query_player,
-                                                        &player_1_member,
-                                                        &player_2_member
-                                                    )
-                                                    .unwrap(),
-                                                    query_player.in_game_representation,
-                                                    chess_move
-                                                ),
-                                            )
-                                            .await?;
-
-                                        // Check for a checkmate.
-                                        if board.checkmate() {
-                                            frontend
-                                                .match_thread
-                                                .say(
-                                                    ctx.http(),
-                                                    format!(
-                                                        "{} checkmated {}. GG.",
-                                                        player_1_member, player_2_member
-                                                    ),
-                                                )
-                                                .await?;
-                                            break;
-                                        }
-
-                                        // Check for a stalemate.
-                                        if board.stalemate() {
-                                            frontend
-                                                .match_thread
-                                                .say(
-                                                    ctx.http(),
-                                                    format!(
-                                                        "{} caused a stalemate {}. GG.",
-                                                        player_1_member, player_2_member
-                                                    ),
-                                                )
-                                                .await?;
-                                            break;
-                                        }
-
-                                        acting_player_colour = get_opposite_colour(
-                                            query_player.in_game_representation,
-                                        );
-
-                                        frontend
-                                            .legal_move_message
-                                            .edit(ctx.http(), |m| {
-                                                m.content(format!(
-                                                    "{}'s legal moves in the current position:\n{:?}",
-                                                    acting_player_colour,
-                                                    board
-                                                        .generate_moves()
-                                                        .iter_mut()
-                                                        .map(|chess_move| chess_move.stringify())
-                                                        .collect::<Vec<String>>()
-                                                ))
-                                            })
-                                            .await?;
-                                    } else {
-                                        return Err(anyhow!("stringified_legal_moves index is out of range of legal_moves. Should be unreacheable").into());
-                                    }
-                                } else {
-                                    frontend
-                                        .match_thread
-                                        .say(
-                                            ctx.http(),
-                                            format!(
-                                        "{} is not a legal move. Use `/move_notation_guide` for help.",
-                                        chess_move
-                                    ),
-                                        )
-                                        .await?;
-                                }
-                            } // else { It is not query_member's turn. }
-                        } else {
-                            frontend
-                                .match_thread
-                                .say(ctx.http(), "You are not a player in this match.")
-                                .await?;
-                        }
-                    }
-                    DiscordCommand::Resign(_) => todo!(),
-                    DiscordCommand::VerifyIfAlreadyInMatch(member, respond_tx) => {
-                        if self
-                            .check_if_member_is_in_game(member, &player_1_member, &player_2_member)
-                            .is_ok()
-                        {
-                            respond_tx.send(true).await?;
+    /// Plays the match until it is over, then tears its frontend down after `THREAD_DELETION_DELAY`.
+    /// A match that stops because of an error keeps its thread and stored copy, so the next start picks it back up.
+    pub async fn run(mut self) -> Result<(), Error> {
+        let outcome = self.play().await;
+
+        // Members are free to start a new match as soon as this one is over.
+        self.data.match_registry.deregister(self.match_id);
+        outcome?;
+
+        tokio::time::sleep(THREAD_DELETION_DELAY).await;
+
+        let closed = self.frontend.close().await;
+        let deleted = self.data.database.delete_match(self.match_id);
+
+        closed?;
+        deleted
+    }
+
+    async fn play(&mut self) -> Result<(), Error> {
+        let mut clock_save = interval_at(Instant::now() + CLOCK_SAVE_INTERVAL, CLOCK_SAVE_INTERVAL);
+        clock_save.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        while self.engine.state() != GameState::GameOver {
+            let deadline = self.engine.deadline();
+            if let (None, Some(level)) = (&self.bot_reply, self.engine.bot_to_move()) {
+                self.bot_reply = Some(BotReply {
+                    position: self.engine.board().zobrist(),
+                    search: think(level, self.engine.board().clone()),
+                });
+            }
+
+            let (mut events, at) = tokio::select! {
+                input = self.match_rx.recv() => {
+                    let Some(MatchInput { command, sent_at }) = input else {
+                        break;
+                    };
+
+                    let events = match command {
+                        DiscordCommand::JoinMatch(joining_user_id) => {
+                            self.engine.join(joining_user_id, sent_at)
                         }
-                    }
-                    DiscordCommand::TimeTick => {
-                        match self
-                            .get_acting_chessplayer(acting_player_colour)
-                            .unwrap()
-                            .in_game_representation
+                        DiscordCommand::MakeMove(query_user_id, chess_move)
+                            if self.engine.is_waiting(query_user_id) =>
                         {
-                            Player::White => {
-                                self.player_time.white -= 1;
+                            if is_readable_move(&chess_move) {
+                                // Replaces any premove the player had already queued.
+                                self.premove = Some(Premove {
+                                    user_id: query_user_id,
+                                    chess_move,
+                                });
+                                Vec::new()
+                            } else {
+                                vec![MatchEvent::UnreadableMove(chess_move)]
                             }
-                            Player::Black => {
-                                self.player_time.black -= 1;
+                        }
+                        DiscordCommand::MakeMove(query_user_id, chess_move) => {
+                            self.engine.make_move(query_user_id, &chess_move, sent_at)
+                        }
+                        DiscordCommand::Resign(query_user_id) => self.engine.resign(query_user_id),
+                        DiscordCommand::OfferDraw(query_user_id) => {
+                            self.engine.offer_draw(query_user_id)
+                        }
+                        DiscordCommand::AcceptDraw(query_user_id) => {
+                            self.engine.accept_draw(query_user_id)
+                        }
+                        DiscordCommand::DeclineDraw(query_user_id) => {
+                            self.engine.decline_draw(query_user_id)
+                        }
+                        DiscordCommand::ClaimDraw(query_user_id) => {
+                            self.engine.claim_draw(query_user_id)
+                        }
+                        DiscordCommand::RequestTakeback(query_user_id) => {
+                            self.engine.request_takeback(query_user_id, sent_at)
+                        }
+                        DiscordCommand::AcceptTakeback(query_user_id, ply, reply_tx) => {
+                            let events = if self.engine.moves().len() == ply {
+                                self.engine.accept_takeback(query_user_id, sent_at)
+                            } else {
+                                Vec::new()
+                            };
+                            let _ = reply_tx.send(events.iter().any(|event| {
+                                matches!(event, MatchEvent::TakebackAccepted { .. })
+                            }));
+                            events
+                        }
+                        DiscordCommand::DeclineTakeback(query_user_id, ply, reply_tx) => {
+                            let events = if self.engine.moves().len() == ply {
+                                self.engine.decline_takeback(query_user_id)
+                            } else {
+                                Vec::new()
+                            };
+                            let _ = reply_tx.send(events.iter().any(|event| {
+                                matches!(event, MatchEvent::TakebackDeclined(_))
+                            }));
+                            events
+                        }
+                        DiscordCommand::PickPromotion(query_user_id, ply, chess_move, reply_tx) => {
+                            let current = self.engine.moves().len() == ply;
+                            let _ = reply_tx.send(current);
+                            if current {
+                                self.engine.make_move(query_user_id, &chess_move, sent_at)
+                            } else {
+                                Vec::new()
                             }
                         }
+                        DiscordCommand::ListLegalMoves(query_user_id, reply_tx) => {
+                            // The asker may have given up waiting, which is fine.
+                            let _ = reply_tx.send(self.engine.legal_moves_for(query_user_id));
+                            Vec::new()
+                        }
+                        DiscordCommand::ViewBoard(query_user_id, reply_tx) => {
+                            let _ = reply_tx.send(self.engine.board_view(query_user_id));
+                            Vec::new()
+                        }
+                        DiscordCommand::CancelPremove(query_user_id, reply_tx) => {
+                            let cancelled = match &self.premove {
+                                Some(premove) if premove.user_id == query_user_id => {
+                                    self.premove.take().map(|premove| premove.chess_move)
+                                }
+                                _ => None,
+                            };
+                            let _ = reply_tx.send(cancelled);
+                            Vec::new()
+                        }
+                    };
 
-                        frontend
-                            .time_info_message
-                            .edit(ctx.http(), |m| {
-                                m.content(format!(
-                                    "{} ({}) Time: {}\n{} ({}) Time: {}",
-                                    player_1_member.user.name,
-                                    self.player_one.in_game_representation,
-                                    self.get_colour_timeleft(
-                                        self.player_one.in_game_representation
-                                    ),
-                                    player_2_member.user.name,
-                                    self.player_two.in_game_representation,
-                                    self.get_colour_timeleft(
-                                        self.player_two.in_game_representation
-                                    ),
-                                ))
-                            })
-                            .await?;
-
-                        if self.get_colour_timeleft(acting_player_colour) == 0 {
-                            frontend
-                                .match_thread
-                                .say(
-                                    ctx.http(),
-                                    format!(
-                                        "{} just lost on time. {} wins. GG.",
-                                        acting_player_colour,
-                                        get_opposite_colour(acting_player_colour)
-                                    ),
-                                )
-                                .await?;
-
-                            time_ticker_task.abort();
-
-                            break;
+                    (events, sent_at)
+                }
+                reply = async { Some((&mut self.bot_reply.as_mut()?.search).await) }, if self.bot_reply.is_some() => {
+                    let now = Instant::now();
+                    let position = self.bot_reply.take().map(|bot_reply| bot_reply.position);
+                    let searched = match reply.transpose() {
+                        Ok(searched) => searched.flatten(),
+                        Err(error) => {
+                            // A search that panicked mustn't end the match, so the bot plays any legal move instead.
+                            tracing::error!("The bot's search in match {} failed: {}", self.match_id, error);
+                            self.engine
+                                .board()
+                                .generate_moves()
+                                .first()
+                                .map(|chess_move| chess_move.stringify())
+                        }
+                    };
+                    let events = match searched {
+                        Some(chess_move) if position == Some(self.engine.board().zobrist()) => {
+                            self.engine.make_bot_move(&chess_move, now)
                         }
-                    }
+                        // The position changed while the bot was thinking, so it thinks again if it is still to move.
+                        _ => Vec::new(),
+                    };
+
+                    (events, now)
+                }
+                // Deadlines are slept on directly, so a flag falls on time no matter how busy the match is.
+                _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    let now = Instant::now();
+                    (self.engine.expire(now), now)
+                }
+                _ = clock_save.tick(), if self.engine.state() == GameState::Playing => {
+                    self.save_progress()?;
+                    continue;
+                }
+            };
+
+            self.play_premove(&mut events, at);
+
+            self.save(&events)?;
+
+            // The game carries on even if Discord can't be reached for a moment. The engine holds the truth, and the
+            // pinned messages catch up with the next event that renders.
+            for event in &events {
+                if let Err(error) = self.frontend.render(&self.engine, event).await {
+                    tracing::warn!(
+                        "Unable to show {:?} in match {}: {}",
+                        event,
+                        self.match_id,
+                        error
+                    );
                 }
             }
         }
 
-        self.end_the_game(ctx, frontend).await?;
         Ok(())
     }
 
-    async fn end_the_game(
-        mut self,
-        ctx: Context<'_>,
-        frontend: MatchFrontend,
-    ) -> Result<(), Error> {
-        self.state = GameState::GameOver;
-
-        frontend
-            .match_thread
-            .say(
-                ctx.http(),
-                "The match is over. Deleting thread in 30 secs...",
-            )
-            .await?;
-
-        tokio::time::sleep(Duration::from_secs(30)).await;
+    /// Plays the queued premove if `events` include the opponent's move. It is played at the same instant, so it
+    /// costs no clock time. A premove that can't be played in the new position is dropped instead.
+    fn play_premove(&mut self, events: &mut Vec<MatchEvent>, at: Instant) {
+        let Some(premove) = &self.premove else {
+            return;
+        };
+
+        // A takeback changes the position the premove was made for.
+        if events
+            .iter()
+            .any(|event| matches!(event, MatchEvent::TakebackAccepted { .. }))
+        {
+            self.premove = None;
+            return;
+        }
 
-        frontend.match_thread.delete(ctx.http()).await?;
-        frontend.match_thread_message.delete(ctx.http()).await?;
+        let opponent_moved = events.iter().any(|event| {
+            matches!(event, MatchEvent::MoveApplied { player, .. } if player.user_id() != Some(premove.user_id))
+        });
+        if !opponent_moved {
+            return;
+        }
 
-        Ok(())
-    }
+        let Some(premove) = self.premove.take() else {
+            return;
+        };
+        if self.engine.state() != GameState::Playing {
+            return;
+        }
 
-    fn get_acting_chessplayer(self, acting_player_colour: Player) -> Result<ChessPlayer, Error> {
-        if self.player_one.in_game_representation == acting_player_colour {
-            Ok(self.player_one)
-        } else if self.player_two.in_game_representation == acting_player_colour {
-            Ok(self.player_two)
-        } else {
-            Err(anyhow!(
-                "Neither of the players matched the turn colour. Should be impossible to reach."
+        let premove_events = self
+            .engine
+            .make_move(premove.user_id, &premove.chess_move, at);
+        if premove_events.iter().any(|event| {
+            matches!(
+                event,
+                MatchEvent::MoveApplied { .. } | MatchEvent::GameEnded { .. }
             )
-            .into())
+        }) {
+            events.extend(premove_events);
+        } else {
+            events.push(MatchEvent::PremoveDropped {
+                user_id: premove.user_id,
+                chess_move: premove.chess_move,
+            });
         }
     }
 
-    fn check_if_member_is_in_game(
-        self,
-        query_member: Box<Member>,
-        player_1_member: &Member,
-        player_2_member: &Member,
-    ) -> Result<ChessPlayer, Error> {
-        if query_member.user.id == player_1_member.user.id {
-            Ok(self.player_one)
-        } else if query_member.user.id == player_2_member.user.id {
-            Ok(self.player_two)
-        } else {
-            Err(anyhow!("This member is not playing in this match.").into())
+    fn save(&self, events: &[MatchEvent]) -> Result<(), Error> {
+        if events.is_empty() {
+            return Ok(());
         }
+
+        self.save_progress()
     }
 
-    fn get_colour_timeleft(self, query_colour: Player) -> i32 {
-        match query_colour {
-            Player::White => self.player_time.white,
-            Player::Black => self.player_time.black,
-        }
+    fn save_progress(&self) -> Result<(), Error> {
+        self.data
+            .database
+            .save_progress(self.match_id, &get_progress(&self.engine, Instant::now())?)
+    }
+}
+
+/// Deletes the thread of a match that couldn't be set up, along with the message it was started from.
+pub(crate) async fn discard_match_thread(
+    http: &Http,
+    match_thread_message: &Message,
+    match_thread: &GuildChannel,
+) {
+    if let Err(error) = match_thread.delete(http).await {
+        tracing::warn!("Unable to delete thread {}: {}", match_thread.id, error);
+    }
+    if let Err(error) = match_thread_message.delete(http).await {
+        tracing::warn!(
+            "Unable to delete the message of thread {}: {}",
+            match_thread.id,
+            error
+        );
     }
 }
 
-pub fn get_opposite_colour(colour: Player) -> Player {
-    if colour == Player::White {
-        Player::Black
-    } else {
-        Player::White
+/// How many members can play in the match: both players, unless one is a bot.
+fn member_seats(engine: &MatchEngine) -> usize {
+    [engine.player_one(), engine.player_two()]
+        .iter()
+        .filter(|player| player.bot().is_none())
+        .count()
+}
+
+/// The stored form of a match's engine, as it stands at `now`.
+pub fn get_progress(engine: &MatchEngine, now: Instant) -> Result<MatchProgress, Error> {
+    let player_one = engine.player_one();
+
+    Ok(MatchProgress {
+        state: engine.state(),
+        opponent_join_deadline: engine.opponent_join_deadline(now),
+        player_one_id: player_one
+            .user_id()
+            .ok_or_else(|| anyhow!("Player 1 has no user id."))?,
+        player_one_colour: player_one.colour(),
+        player_two: engine.player_two().seat,
+        clock: engine.clock().snapshot(now),
+        start_fen: engine.start_fen().map(str::to_string),
+        rated: engine.rated(),
+        moves: engine.moves().to_vec(),
+        move_clocks: engine.move_clocks().to_vec(),
+    })
+}
+
+fn get_record(
+    match_id: MatchId,
+    frontend: &DiscordFrontend,
+    progress: MatchProgress,
+) -> MatchRecord {
+    MatchRecord {
+        match_id,
+        guild_id: frontend.match_thread().guild_id,
+        parent_channel_id: frontend.match_thread_message().channel_id,
+        match_thread_message_id: frontend.match_thread_message().id,
+        board_message_id: frontend.board_message().id,
+        time_info_message_id: frontend.time_info_message().id,
+        legal_move_message_id: frontend.legal_move_message().id,
+        progress,
     }
 }
 
-fn get_member_from_chessplayer(
-    query_chessplayer: ChessPlayer,
-    player_1_member: &Member,
-    player_2_member: &Member,
-) -> Result<Member, Error> {
-    if query_chessplayer.user_id.unwrap() == player_1_member.user.id {
-        Ok(player_1_member.clone())
-    } else if query_chessplayer.user_id.unwrap() == player_2_member.user.id {
-        Ok(player_2_member.clone())
-    } else {
-        Err(
-            anyhow!("Chessplayer user id did not match either of the two members in this match.")
-                .into(),
+/// Rebuilds an engine by replaying the stored moves from the starting position.
+/// Its deadlines and clocks carry on from `now`, so time the bot was down for isn't counted.
+fn engine_from_progress(progress: &MatchProgress, now: Instant) -> Result<MatchEngine, Error> {
+    let mut board = match &progress.start_fen {
+        Some(fen) => read_fen(fen)?,
+        None => Board::start_pos(),
+    };
+    if progress.move_clocks.len() != progress.moves.len() {
+        return Err(anyhow!(
+            "{} stored moves came with {} clock readings.",
+            progress.moves.len(),
+            progress.move_clocks.len()
         )
+        .into());
+    }
+
+    let mut positions = vec![board.zobrist()];
+    for chess_move in &progress.moves {
+        if !board.apply_uci_move(chess_move) {
+            return Err(anyhow!("Stored move {} could not be replayed.", chess_move).into());
+        }
+        positions.push(board.zobrist());
+    }
+
+    let mut clock = progress.clock.clone();
+    if progress.state == GameState::Playing {
+        clock.start(now);
     }
+
+    Ok(MatchEngine::builder()
+        .state(progress.state)
+        .join_deadline(now + Duration::from_secs(progress.opponent_join_deadline.max(0) as u64))
+        .player_one(
+            ChessPlayer::builder()
+                .seat(Seat::Member(progress.player_one_id))
+                .player_slot(PlayerSlot::Player1)
+                .in_game_representation(progress.player_one_colour)
+                .build(),
+        )
+        .player_two(
+            ChessPlayer::builder()
+                .seat(progress.player_two)
+                .player_slot(PlayerSlot::Player2)
+                .in_game_representation(get_opposite_colour(progress.player_one_colour))
+                .build(),
+        )
+        .clock(clock)
+        .board(board)
+        .and_start_fen(progress.start_fen.clone())
+        .rated(progress.rated)
+        .moves(progress.moves.clone())
+        .positions(positions)
+        .move_clocks(progress.move_clocks.clone())
+        .build())
 }
@@ -0,0 +1,235 @@
+use std::{fmt, time::Duration};
+
+use pleco::Player;
+use tokio::time::Instant;
+
+use crate::engine::get_opposite_colour;
+
+/// How much time each player starts with, and how much they get back for each of their moves. Both are in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    pub base: i32,
+    /// The increment or delay, depending on `mode`.
+    pub increment: i32,
+    pub mode: ClockMode,
+}
+
+/// How the increment of a time control is given back to the players.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ClockMode {
+    /// The full increment is added after every move.
+    #[name = "Fischer increment"]
+    Fischer,
+    /// The clock only starts counting down once the delay has passed.
+    #[name = "US delay"]
+    UsDelay,
+    /// The time used on a move is added back after it, up to the delay.
+    #[name = "Bronstein delay"]
+    Bronstein,
+}
+
+/// Both players' clocks.
+///
+/// Time is only settled when a move is made, from the `Instant` the turn started at, so the clocks never drift
+/// however late the match gets around to looking at them.
+#[derive(Debug, Clone, PartialEq, buildstructor::Builder)]
+pub struct ChessClock {
+    time_control: TimeControl,
+    /// What White had left when their current or last turn started.
+    white: Duration,
+    black: Duration,
+    /// Time the player to move had already spent on this move before `running_since`, e.g. before a restart.
+    turn_time: Duration,
+    to_move: Player,
+    /// When the clock of the player to move was started, unless the clock is stopped.
+    running_since: Option<Instant>,
+}
+
+impl TimeControl {
+    /// A time control with a Fischer increment.
+    pub const fn new(base: i32, increment: i32) -> Self {
+        Self {
+            base,
+            increment,
+            mode: ClockMode::Fischer,
+        }
+    }
+
+    pub const fn with_mode(self, mode: ClockMode) -> Self {
+        Self { mode, ..self }
+    }
+
+    /// Bullet, Blitz, Rapid or Classical, going by how long a 40 move game would take.
+    pub fn category(&self) -> &'static str {
+        match self.base + 40 * self.increment {
+            ..=179 => "Bullet",
+            180..=479 => "Blitz",
+            480..=1499 => "Rapid",
+            _ => "Classical",
+        }
+    }
+}
+
+impl Default for TimeControl {
+    /// 5+0, the only time control before they became selectable.
+    fn default() -> Self {
+        Self::new(300, 0)
+    }
+}
+
+impl fmt::Display for TimeControl {
+    /// Formats the control the usual way, as base minutes + increment seconds, e.g. `3+2`.
+    /// Delays are spelled out, e.g. `3 d2` for a US delay and `3 b2` for a Bronstein delay.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let base_minutes = f64::from(self.base) / 60.0;
+
+        match self.mode {
+            ClockMode::Fischer => write!(f, "{}+{}", base_minutes, self.increment),
+            ClockMode::UsDelay => write!(f, "{} d{}", base_minutes, self.increment),
+            ClockMode::Bronstein => write!(f, "{} b{}", base_minutes, self.increment),
+        }
+    }
+}
+
+impl ChessClock {
+    pub fn time_control(&self) -> TimeControl {
+        self.time_control
+    }
+
+    /// Starts the clock of the player to move, carrying over any time they had already spent on this move.
+    pub fn start(&mut self, now: Instant) {
+        self.running_since = Some(now);
+    }
+
+    /// A stopped copy of the clock as it reads at `now`, which can be stored and started again later.
+    pub fn snapshot(&self, now: Instant) -> ChessClock {
+        ChessClock {
+            turn_time: self.turn_time(now),
+            running_since: None,
+            ..self.clone()
+        }
+    }
+
+    pub fn to_move(&self) -> Player {
+        self.to_move
+    }
+
+    /// How long the player to move has spent on the current move so far.
+    pub fn turn_time(&self, now: Instant) -> Duration {
+        match self.running_since {
+            Some(since) => self.turn_time + now.saturating_duration_since(since),
+            None => self.turn_time,
+        }
+    }
+
+    /// What `colour` has left at `now`.
+    pub fn remaining(&self, colour: Player, now: Instant) -> Duration {
+        let remaining = self.remaining_at_turn_start(colour);
+
+        if colour == self.to_move {
+            remaining.saturating_sub(self.charged(self.turn_time(now)))
+        } else {
+            remaining
+        }
+    }
+
+    /// What `colour` had left when their current or last turn started.
+    pub fn remaining_at_turn_start(&self, colour: Player) -> Duration {
+        match colour {
+            Player::White => self.white,
+            Player::Black => self.black,
+        }
+    }
+
+    /// Whether `colour` has run out of time.
+    pub fn is_flagged(&self, colour: Player, now: Instant) -> bool {
+        self.remaining(colour, now).is_zero()
+    }
+
+    /// When the running clock will run out, if nobody moves before then.
+    pub fn flag_deadline(&self) -> Option<Instant> {
+        let since = self.running_since?;
+        let mut available = self.remaining_at_turn_start(self.to_move);
+        if self.time_control.mode == ClockMode::UsDelay {
+            available += self.delay();
+        }
+
+        Some(since + available.saturating_sub(self.turn_time))
+    }
+
+    /// Ends the turn of the player whose clock is running, gives them their increment, and starts their opponent's clock.
+    pub fn press(&mut self, now: Instant) {
+        if self.running_since.is_none() {
+            return;
+        }
+
+        let colour = self.to_move;
+        let turn_time = self.turn_time(now);
+        let bonus = match self.time_control.mode {
+            ClockMode::Fischer => self.delay(),
+            ClockMode::UsDelay => Duration::ZERO,
+            ClockMode::Bronstein => turn_time.min(self.delay()),
+        };
+
+        let remaining = self.remaining(colour, now) + bonus;
+        *self.remaining_at_turn_start_mut(colour) = remaining;
+        self.turn_time = Duration::ZERO;
+        self.to_move = get_opposite_colour(colour);
+        self.running_since = Some(now);
+    }
+
+    /// Sets the clocks back to an earlier turn of `to_move`, which starts again at `now`.
+    pub fn set_back(&mut self, white: Duration, black: Duration, to_move: Player, now: Instant) {
+        self.white = white;
+        self.black = black;
+        self.turn_time = Duration::ZERO;
+        self.to_move = to_move;
+        self.running_since = Some(now);
+    }
+
+    /// The part of a move's time that comes off the clock.
+    fn charged(&self, turn_time: Duration) -> Duration {
+        match self.time_control.mode {
+            ClockMode::UsDelay => turn_time.saturating_sub(self.delay()),
+            ClockMode::Fischer | ClockMode::Bronstein => turn_time,
+        }
+    }
+
+    /// The increment or delay.
+    fn delay(&self) -> Duration {
+        Duration::from_secs(self.time_control.increment.max(0) as u64)
+    }
+
+    fn remaining_at_turn_start_mut(&mut self, colour: Player) -> &mut Duration {
+        match colour {
+            Player::White => &mut self.white,
+            Player::Black => &mut self.black,
+        }
+    }
+}
+
+impl ChessClock {
+    /// Both clocks at the start of a match in which `first` moves first, stopped until the opponent joins.
+    pub fn starting_with(time_control: TimeControl, first: Player) -> Self {
+        Self {
+            to_move: first,
+            ..Self::from(time_control)
+        }
+    }
+}
+
+impl From<TimeControl> for ChessClock {
+    /// Both clocks at the start of a match, stopped until the opponent joins.
+    fn from(time_control: TimeControl) -> Self {
+        let base = Duration::from_secs(time_control.base.max(0) as u64);
+
+        Self {
+            time_control,
+            white: base,
+            black: base,
+            turn_time: Duration::ZERO,
+            to_move: Player::White,
+            running_since: None,
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use pleco::Player;
+
+use crate::{
+    board_image::{Orientation, PieceSet},
+    board_style::{BoardContent, BoardLook},
+    render_queue::board_attachment,
+    Context, Error,
+};
+
+/// Show the board of your match, or of the match in this thread, from your side.
+#[poise::command(
+    slash_command,
+    required_permissions = "USE_SLASH_COMMANDS",
+    required_bot_permissions = "VIEW_CHANNEL | SEND_MESSAGES | MANAGE_MESSAGES | EMBED_LINKS | ATTACH_FILES | READ_MESSAGE_HISTORY | USE_SLASH_COMMANDS | MANAGE_THREADS | CREATE_PUBLIC_THREADS | CREATE_PRIVATE_THREADS | SEND_MESSAGES_IN_THREADS",
+    user_cooldown = "5",
+    guild_only,
+    ephemeral = "true"
+)]
+pub async fn board(
+    ctx: Context<'_>,
+    #[description = "Which side to show at the bottom. Defaults to your own."] orientation: Option<
+        Orientation,
+    >,
+    #[description = "How to draw the pieces, when this server shows boards as images."]
+    piece_set: Option<PieceSet>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let view = ctx
+        .data()
+        .match_registry
+        .view_board(guild_id, ctx.author().id, ctx.channel_id())
+        .await?;
+
+    // Spectators see the board from White's side unless they ask otherwise.
+    let orientation = orientation
+        .map(Player::from)
+        .or(view.colour)
+        .unwrap_or(Player::White);
+    let board_look = match BoardLook::for_guild(
+        ctx.serenity_context().http.as_ref(),
+        guild_id,
+        ctx.data().database.board_style(guild_id)?,
+    )
+    .await
+    {
+        BoardLook::Image(_) => BoardLook::Image(piece_set.unwrap_or_default()),
+        board_look => board_look,
+    };
+
+    match board_look.draw(&view.board, orientation)? {
+        BoardContent::Image(png) => {
+            ctx.send(|m| m.attachment(board_attachment(&png)).ephemeral(true))
+                .await?;
+        }
+        BoardContent::Text(text) => {
+            ctx.send(|m| m.content(text).ephemeral(true)).await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use crate::{board_style::BoardStyle, Context, Error};
+
+/// Choose how boards are shown in this server, e.g. as text where the bot may not post images.
+#[poise::command(
+    slash_command,
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "VIEW_CHANNEL | SEND_MESSAGES | USE_SLASH_COMMANDS",
+    user_cooldown = "5",
+    guild_only,
+    ephemeral = "true"
+)]
+pub async fn board_style(
+    ctx: Context<'_>,
+    #[description = "Image, Unicode text, or this server's piece emoji (named wk, wq, ... bp)."]
+    style: BoardStyle,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    ctx.data().database.set_board_style(guild_id, style)?;
+
+    ctx.say(format!(
+        "Boards in this server are now shown as: {}. Matches already being played keep their style until the bot restarts.",
+        style
+    ))
+    .await?;
+
+    Ok(())
+}
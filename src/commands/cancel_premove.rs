@@ -0,0 +1,34 @@
+use anyhow::anyhow;
+
+use crate::{Context, Error};
+
+/// Cancel the move you queued to be played once your opponent moves.
+#[poise::command(
+    slash_command,
+    required_permissions = "USE_SLASH_COMMANDS",
+    required_bot_permissions = "VIEW_CHANNEL | SEND_MESSAGES | USE_SLASH_COMMANDS",
+    guild_only,
+    ephemeral = "true"
+)]
+pub async fn cancel_premove(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let cancelled = ctx
+        .data()
+        .match_registry
+        .cancel_premove(guild_id, ctx.author().id)
+        .await
+        .map_err(|_| anyhow!("You are not playing in a match in this server."))?;
+
+    let reply = match cancelled {
+        Some(chess_move) => format!("Cancelled your premove {}.", chess_move),
+        None => "You have no premove to cancel.".to_string(),
+    };
+    ctx.say(reply).await?;
+
+    Ok(())
+}
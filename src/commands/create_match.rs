@@ -1,25 +1,84 @@
 use std::{borrow::Cow, time::Duration};
 
 use anyhow::anyhow;
-use pleco::Player;
-use poise::serenity_prelude::{CacheHttp, ChannelType::PublicThread, CreateThread, Member};
-use tokio::{sync::mpsc, time::timeout};
+use pleco::{Board, Player};
+use poise::serenity_prelude::{
+    CacheHttp, ChannelType::PublicThread, CreateThread, GuildChannel, Member, Message, UserId,
+};
+use tokio::time::Instant;
 
 use crate::{
-    chess_match::{
-        get_opposite_colour, ChessMatch, ChessPlayer, GameState, PlayerSlot, PlayerTime,
+    chess_match::{discard_match_thread, ChessMatch, MatchContext},
+    clock::{ChessClock, ClockMode, TimeControl},
+    engine::{
+        get_opposite_colour, insufficient_material, ChessPlayer, GameState, MatchEngine,
+        PlayerSlot, Seat,
     },
-    Context, DiscordCommand, DiscordCommunication, Error,
+    match_registry::Reservation,
+    notation::read_fen,
+    Context, Error,
 };
 
+/// How long a new match waits for an opponent before it is aborted.
+pub const OPPONENT_JOIN_DEADLINE: Duration = Duration::from_secs(90);
+
+/// The time controls offered by `/create_match`, written as base minutes + increment seconds.
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum TimeControlPreset {
+    #[name = "1+0 Bullet"]
+    OneMinute,
+    #[name = "3+2 Blitz"]
+    ThreeMinutesTwoSeconds,
+    #[name = "5+0 Blitz"]
+    FiveMinutes,
+    #[name = "10+5 Rapid"]
+    TenMinutesFiveSeconds,
+    #[name = "15+10 Rapid"]
+    FifteenMinutesTenSeconds,
+    #[name = "30+0 Classical"]
+    ThirtyMinutes,
+}
+
+impl TimeControlPreset {
+    pub fn time_control(self) -> TimeControl {
+        match self {
+            TimeControlPreset::OneMinute => TimeControl::new(60, 0),
+            TimeControlPreset::ThreeMinutesTwoSeconds => TimeControl::new(180, 2),
+            TimeControlPreset::FiveMinutes => TimeControl::new(300, 0),
+            TimeControlPreset::TenMinutesFiveSeconds => TimeControl::new(600, 5),
+            TimeControlPreset::FifteenMinutesTenSeconds => TimeControl::new(900, 10),
+            TimeControlPreset::ThirtyMinutes => TimeControl::new(1800, 0),
+        }
+    }
+}
+
 /// Create a chess match in a public thread. Opponents can join using /join_match.
 #[poise::command(
     slash_command,
     required_permissions = "USE_SLASH_COMMANDS",
-    required_bot_permissions = "VIEW_CHANNEL | SEND_MESSAGES | MANAGE_MESSAGES | EMBED_LINKS | READ_MESSAGE_HISTORY | USE_SLASH_COMMANDS | MANAGE_THREADS | CREATE_PUBLIC_THREADS | CREATE_PRIVATE_THREADS | SEND_MESSAGES_IN_THREADS",
+    required_bot_permissions = "VIEW_CHANNEL | SEND_MESSAGES | MANAGE_MESSAGES | EMBED_LINKS | ATTACH_FILES | READ_MESSAGE_HISTORY | USE_SLASH_COMMANDS | MANAGE_THREADS | CREATE_PUBLIC_THREADS | CREATE_PRIVATE_THREADS | SEND_MESSAGES_IN_THREADS",
     user_cooldown = "30"
 )]
-pub async fn create_match(ctx: Context<'_>) -> Result<(), Error> {
+pub async fn create_match(
+    ctx: Context<'_>,
+    #[description = "A preset time control. Defaults to 5+0."] time_control: Option<
+        TimeControlPreset,
+    >,
+    #[description = "Custom time control: minutes on each clock."]
+    #[min = 1]
+    #[max = 180]
+    base_minutes: Option<u32>,
+    #[description = "Custom time control: seconds of increment or delay per move."]
+    #[min = 0]
+    #[max = 60]
+    increment_seconds: Option<u32>,
+    #[description = "Whether the increment is added after each move or used as a delay. Defaults to Fischer increment."]
+    clock_mode: Option<ClockMode>,
+    #[description = "Start from this position, written as FEN, instead of the standard one."]
+    fen: Option<String>,
+    #[description = "Whether the match is rated. Rated matches don't allow takebacks. Defaults to casual."]
+    rated: Option<bool>,
+) -> Result<(), Error> {
     ctx.defer_ephemeral().await?;
 
     let creating_member: Cow<'_, Member>;
@@ -30,50 +89,91 @@ pub async fn create_match(ctx: Context<'_>) -> Result<(), Error> {
         return Err(anyhow!("Unable to get Player_1 Member").into());
     }
 
-    // Check if member is already in a match within this guild.
-    let (response_channel_tx, mut response_channel_rx) = mpsc::channel(1);
-    ctx.data()
-        .system_communication_channel
-        .0
-        .send(DiscordCommunication(
-            creating_member.guild_id,
-            DiscordCommand::VerifyIfAlreadyInMatch(
-                Box::new(creating_member.clone().into_owned()),
-                response_channel_tx,
-            ),
-        ))?;
-    match timeout(Duration::from_secs(10), response_channel_rx.recv()).await {
-        Ok(_) => {
-            ctx.say("You are already in a match. Threadrook currently only supports users competing in a single match at a time per server.").await?;
-            return Ok(());
-        }
-        Err(_) => {
-            ctx.say("Creating match...").await?;
-        }
-    }
+    // The member's place is held while the thread is set up, so a second invocation can't start another match.
+    let Ok(reservation) = ctx
+        .data()
+        .match_registry
+        .reserve(creating_member.guild_id, creating_member.user.id)
+    else {
+        ctx.say("You are already in a match. Threadrook currently only supports users competing in a single match at a time per server.").await?;
+        return Ok(());
+    };
+
+    let time_control = choose_time_control(time_control, base_minutes, increment_seconds)?
+        .with_mode(clock_mode.unwrap_or(ClockMode::Fischer));
+    let start = start_position(fen.as_deref())?;
+    let rated = rated.unwrap_or(false);
+    let from_position = if fen.is_some() {
+        " from a custom position"
+    } else {
+        ""
+    };
+
+    ctx.say("Creating match...").await?;
+
+    let announcement = format!(
+        "{} just created a {}{} chess match{}! Use `/join_match` to join.",
+        ctx.author().name,
+        if rated { "rated " } else { "" },
+        time_control,
+        from_position,
+    );
+    let thread_name = format!(
+        "{}'s ThreadRook Chess Match ({} {})",
+        ctx.author().name,
+        time_control,
+        time_control.category(),
+    );
+    let (match_thread_message, match_thread) =
+        open_match_thread(ctx, announcement, thread_name).await?;
+
+    let player_1_colour = if rand::random() {
+        Player::White
+    } else {
+        Player::Black
+    };
+
+    let engine = new_match_engine(
+        creating_member.user.id,
+        player_1_colour,
+        time_control,
+        start,
+        rated,
+    );
 
-    let match_thread_message = ctx
-        .say(format!(
-            "{} just created a chess match! Use `/join_match` to join.",
-            ctx.author().name,
-        ))
-        .await?;
+    start_match(ctx, engine, match_thread_message, match_thread, reservation).await?;
+
+    Ok(())
+}
 
-    let match_thread_message_clone = match_thread_message.clone();
+/// Announces a match in the channel the command was used in, and opens its thread with a welcome for the author.
+pub(crate) async fn open_match_thread(
+    ctx: Context<'_>,
+    announcement: String,
+    thread_name: String,
+) -> Result<(Message, GuildChannel), Error> {
+    let match_thread_message = ctx.say(announcement).await?.into_message().await?;
 
-    let match_thread = ctx
+    let match_thread = match ctx
         .channel_id()
         .create_public_thread(
             ctx.http(),
-            match_thread_message.into_message().await?,
-            |t| -> &mut CreateThread {
-                t.name(format!("{}'s ThreadRook Chess Match", ctx.author().name,))
-                    .kind(PublicThread)
-            },
+            match_thread_message.id,
+            |t| -> &mut CreateThread { t.name(thread_name).kind(PublicThread) },
         )
-        .await?;
+        .await
+    {
+        Ok(match_thread) => match_thread,
+        Err(error) => {
+            // The announcement would point at a match that doesn't exist.
+            if let Err(error) = match_thread_message.delete(ctx.http()).await {
+                tracing::warn!("Unable to delete a match announcement: {}", error);
+            }
+            return Err(error.into());
+        }
+    };
 
-    match_thread
+    let welcome = match_thread
         .say(
             ctx.http(),
             format!(
@@ -82,50 +182,123 @@ pub async fn create_match(ctx: Context<'_>) -> Result<(), Error> {
         \nWelcome!
         \n`/create_match` to create your own match. 
         \n`/join_match` to join the match of any user that is looking for an opponent. 
-        \n`/make_move` to make a chess move.
+        \n`/play_bot` to play against one of ThreadRook's bots.
+        \n`/make_move` to make a chess move. Moves made before your turn are premoves, which `/cancel_premove` takes back.
         \n`/move_notation_guide` for a quick guide on Threadrook chess move notation. 
-        \n`/resign` to forfeit. 
+        \n`/resign` to forfeit.
+        \n`/offer_draw` to offer a draw, and `/accept_draw` or `/decline_draw` to answer one.
+        \n`/claim_draw` to claim a draw by threefold repetition or the fifty-move rule. 
+        \n`/takeback` to ask your opponent to take back your last move.
         \nLearn more about ThreadRook at https://github.com/idanmuze/threadrook",
                 ctx.author().id
             ),
         )
-        .await?;
+        .await;
+    if let Err(error) = welcome {
+        discard_match_thread(ctx.http(), &match_thread_message, &match_thread).await;
+        return Err(error.into());
+    }
 
-    let player_1_colour = if rand::random() {
-        Player::White
-    } else {
-        Player::Black
+    Ok((match_thread_message, match_thread))
+}
+
+/// Sets the match up in its thread, then plays it on its own task so the command can return.
+pub(crate) async fn start_match(
+    ctx: Context<'_>,
+    engine: MatchEngine,
+    match_thread_message: Message,
+    match_thread: GuildChannel,
+    reservation: Reservation,
+) -> Result<(), Error> {
+    let match_ctx = MatchContext::new(ctx.serenity_context().http.clone(), ctx.data().clone());
+    let chess_match = ChessMatch::start(
+        engine,
+        match_ctx,
+        match_thread_message,
+        match_thread,
+        reservation,
+    )
+    .await?;
+
+    tokio::spawn(async move {
+        if let Err(error) = chess_match.run().await {
+            tracing::error!("Match stopped because of an error: {}", error);
+        }
+    });
+
+    Ok(())
+}
+
+/// Picks the preset, or builds a custom control if either custom option was given.
+fn choose_time_control(
+    preset: Option<TimeControlPreset>,
+    base_minutes: Option<u32>,
+    increment_seconds: Option<u32>,
+) -> Result<TimeControl, Error> {
+    match (preset, base_minutes, increment_seconds) {
+        (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+            Err(anyhow!("Choose either a preset time control or a custom one, not both.").into())
+        }
+        (Some(preset), None, None) => Ok(preset.time_control()),
+        (None, None, None) => Ok(TimeControl::default()),
+        (None, base_minutes, increment_seconds) => {
+            let default = TimeControl::default();
+            Ok(TimeControl::new(
+                base_minutes.map_or(default.base, |minutes| minutes as i32 * 60),
+                increment_seconds.map_or(default.increment, |seconds| seconds as i32),
+            ))
+        }
+    }
+}
+
+/// The position a match starts from: the standard one, or the one in `fen` if it can still be played on from.
+pub fn start_position(fen: Option<&str>) -> Result<Board, Error> {
+    let Some(fen) = fen else {
+        return Ok(Board::start_pos());
     };
 
-    let player_2_colour = get_opposite_colour(player_1_colour);
+    let board = read_fen(fen)?;
+    if board.generate_moves().is_empty() {
+        return Err(anyhow!("The side to move has no legal moves in that position.").into());
+    }
+    if insufficient_material(&board) {
+        return Err(anyhow!("Neither side can checkmate in that position.").into());
+    }
+
+    Ok(board)
+}
+
+/// The engine of a freshly created match, waiting for an opponent to join.
+pub fn new_match_engine(
+    creating_user_id: UserId,
+    player_1_colour: Player,
+    time_control: TimeControl,
+    start: Board,
+    rated: bool,
+) -> MatchEngine {
+    let start_fen = Some(start.fen()).filter(|fen| *fen != Board::start_pos().fen());
 
-    let chess_match = ChessMatch::builder()
+    MatchEngine::builder()
         .state(GameState::WaitingForOpponent)
-        .opponent_join_deadline(90)
+        .join_deadline(Instant::now() + OPPONENT_JOIN_DEADLINE)
         .player_one(
             ChessPlayer::builder()
-                .user_id(creating_member.user.id)
+                .seat(Seat::Member(creating_user_id))
                 .player_slot(PlayerSlot::Player1)
                 .in_game_representation(player_1_colour)
                 .build(),
         )
         .player_two(
             ChessPlayer::builder()
+                .seat(Seat::Open)
                 .player_slot(PlayerSlot::Player2)
-                .in_game_representation(player_2_colour)
+                .in_game_representation(get_opposite_colour(player_1_colour))
                 .build(),
         )
-        .player_time(PlayerTime::builder().white(300).black(300).build())
-        .build();
-
-    chess_match
-        .spawn(
-            ctx,
-            match_thread_message_clone.into_message().await?,
-            match_thread,
-            Box::new(creating_member.clone().into_owned()),
-        )
-        .await?;
-
-    Ok(())
+        .clock(ChessClock::starting_with(time_control, start.turn()))
+        .positions(vec![start.zobrist()])
+        .board(start)
+        .and_start_fen(start_fen)
+        .rated(rated)
+        .build()
 }
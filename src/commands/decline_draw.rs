@@ -0,0 +1,40 @@
+use std::borrow::Cow;
+
+use anyhow::anyhow;
+use poise::serenity_prelude::Member;
+
+use crate::{Context, DiscordCommand, Error};
+
+/// Decline your opponent's draw offer.
+#[poise::command(
+    slash_command,
+    required_permissions = "USE_SLASH_COMMANDS",
+    required_bot_permissions = "VIEW_CHANNEL | SEND_MESSAGES | MANAGE_MESSAGES | EMBED_LINKS | READ_MESSAGE_HISTORY | USE_SLASH_COMMANDS | MANAGE_THREADS | CREATE_PUBLIC_THREADS | CREATE_PRIVATE_THREADS | SEND_MESSAGES_IN_THREADS",
+    global_cooldown = "5",
+    ephemeral = "true"
+)]
+pub async fn decline_draw(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let declining_member: Cow<'_, Member>;
+
+    if let Some(member) = ctx.author_member().await {
+        declining_member = member
+    } else {
+        return Err(anyhow!("Unable to get Member").into());
+    }
+
+    ctx.data()
+        .match_registry
+        .send_to_member(
+            declining_member.guild_id,
+            declining_member.user.id,
+            DiscordCommand::DeclineDraw(declining_member.user.id),
+        )
+        .await
+        .map_err(|_| anyhow!("You are not playing in a match in this server."))?;
+
+    ctx.say("Declining the draw...").await?;
+
+    Ok(())
+}
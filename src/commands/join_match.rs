@@ -3,7 +3,7 @@ use std::borrow::Cow;
 use anyhow::anyhow;
 use poise::serenity_prelude::Member;
 
-use crate::{Context, DiscordCommand, DiscordCommunication, Error};
+use crate::{Context, Error};
 
 /// Join the match of any user that is looking for an opponent.
 #[poise::command(
@@ -27,19 +27,13 @@ pub async fn join_match(
         return Err(anyhow!("Unable to get Member").into());
     }
 
-    ctx.say(format!("Joining {}'s match...", member.user.name))
+    ctx.data()
+        .match_registry
+        .join(member.guild_id, member.user.id, joining_member.user.id)
         .await?;
 
-    ctx.data()
-        .system_communication_channel
-        .0
-        .send(DiscordCommunication(
-            member.guild_id,
-            DiscordCommand::JoinMatch(
-                Box::new(member.to_owned()),
-                Box::new(joining_member.into_owned()),
-            ),
-        ))?;
+    ctx.say(format!("Joining {}'s match...", member.user.name))
+        .await?;
 
     Ok(())
 }
@@ -1,11 +1,46 @@
 use std::borrow::Cow;
 
 use anyhow::anyhow;
-use poise::serenity_prelude::Member;
+use poise::{serenity_prelude::Member, AutocompleteChoice};
 
-use crate::{Context, DiscordCommand, DiscordCommunication, Error};
+use crate::{Context, DiscordCommand, Error};
 
-/// Make a chess move.
+/// Discord shows at most 25 autocomplete choices.
+const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+
+/// Suggests the caller's legal moves that start with what they have typed so far, in SAN and UCI.
+async fn autocomplete_chess_move(
+    ctx: Context<'_>,
+    partial: &str,
+) -> Vec<AutocompleteChoice<String>> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Vec::new();
+    };
+
+    let mut legal_moves = ctx
+        .data()
+        .match_registry
+        .legal_moves(guild_id, ctx.author().id)
+        .await
+        .unwrap_or_default();
+    legal_moves.sort_by(|a, b| a.san.cmp(&b.san));
+
+    let partial = partial.trim().to_lowercase();
+    legal_moves
+        .into_iter()
+        .filter(|legal_move| {
+            legal_move.san.to_lowercase().starts_with(&partial)
+                || legal_move.uci.starts_with(&partial)
+        })
+        .take(MAX_AUTOCOMPLETE_CHOICES)
+        .map(|legal_move| AutocompleteChoice {
+            name: format!("{} ({})", legal_move.san, legal_move.uci),
+            value: legal_move.san,
+        })
+        .collect()
+}
+
+/// Make a chess move. Moves made before it is your turn are played as soon as your opponent moves.
 #[poise::command(
     slash_command,
     required_permissions = "USE_SLASH_COMMANDS",
@@ -14,7 +49,9 @@ use crate::{Context, DiscordCommand, DiscordCommunication, Error};
 )]
 pub async fn make_move(
     ctx: Context<'_>,
-    #[description = "The move you wish to make."] chess_move: String,
+    #[description = "The move you wish to make, e.g. e4, Nf3 or O-O."]
+    #[autocomplete = "autocomplete_chess_move"]
+    chess_move: String,
 ) -> Result<(), Error> {
     ctx.defer_ephemeral().await?;
 
@@ -27,14 +64,20 @@ pub async fn make_move(
     }
 
     ctx.data()
-        .system_communication_channel
-        .0
-        .send(DiscordCommunication(
+        .match_registry
+        .send_to_member(
             query_member.guild_id,
-            DiscordCommand::MakeMove(Box::new(query_member.into_owned()), chess_move.clone()),
-        ))?;
+            query_member.user.id,
+            DiscordCommand::MakeMove(query_member.user.id, chess_move.clone()),
+        )
+        .await
+        .map_err(|_| anyhow!("You are not playing in a match in this server."))?;
 
-    ctx.say(format!("Making move: {}...", chess_move)).await?;
+    ctx.say(format!(
+        "Making move: {}... If it isn't your turn yet, it will be played as soon as your opponent moves. Use `/cancel_premove` to change your mind.",
+        chess_move
+    ))
+    .await?;
 
     Ok(())
 }
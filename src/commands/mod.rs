@@ -1,5 +1,15 @@
+pub mod accept_draw;
+pub mod board;
+pub mod board_style;
+pub mod cancel_premove;
+pub mod claim_draw;
 pub mod create_match;
+pub mod decline_draw;
 pub mod join_match;
 pub mod make_move;
 pub mod move_notation_guide;
+pub mod offer_draw;
+pub mod play_bot;
+pub mod promotion;
 pub mod resign;
+pub mod takeback;
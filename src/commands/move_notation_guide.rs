@@ -12,7 +12,7 @@ pub async fn move_notation_guide(ctx: Context<'_>) -> Result<(), Error> {
     ctx.defer_ephemeral().await?;
 
     ctx.send(|m| {
-        m.content("The chess move format is 'Source Square, Destination Square, (Promo Piece)'.\n\ne.g. Moving a Queen from A1 to B8 will stringify to `a1b8`.\n\nIf there is a pawn promotion involved, the piece promoted to will be appended to the end of the string, alike `a7a8q` in the case of a queen promotion.\n\nCapital Letters represent white pieces, while lower case represents black pieces.\n\nFor more help click here: https://github.com/idanmuze/threadrook/blob/master/move_guide.md").ephemeral(true)
+        m.content("Moves are written in Standard Algebraic Notation (SAN), e.g. `e4`, `Nf3`, `exd5`, `Nbd7`, `O-O` or `e8=Q`.\n\nPieces are written `K`, `Q`, `R`, `B` and `N`. Pawn moves are just the square they move to. `+` and `#` are optional.\n\nSource Square, Destination Square moves like `g1f3` or `a7a8q` work as well.\n\nFor more help click here: https://github.com/idanmuze/threadrook/blob/master/move_guide.md").ephemeral(true)
     }).await?;
 
     Ok(())
@@ -0,0 +1,118 @@
+use std::borrow::Cow;
+
+use anyhow::anyhow;
+use pleco::{Board, Player};
+use poise::serenity_prelude::{Member, UserId};
+use tokio::time::Instant;
+
+use crate::{
+    bot::BotLevel,
+    clock::{ChessClock, TimeControl},
+    commands::create_match::{open_match_thread, start_match, TimeControlPreset},
+    engine::{get_opposite_colour, ChessPlayer, GameState, MatchEngine, PlayerSlot, Seat},
+    frontend::bot_name,
+    Context, Error,
+};
+
+/// Play a match against one of ThreadRook's bots in a public thread.
+#[poise::command(
+    slash_command,
+    required_permissions = "USE_SLASH_COMMANDS",
+    required_bot_permissions = "VIEW_CHANNEL | SEND_MESSAGES | MANAGE_MESSAGES | EMBED_LINKS | ATTACH_FILES | READ_MESSAGE_HISTORY | USE_SLASH_COMMANDS | MANAGE_THREADS | CREATE_PUBLIC_THREADS | CREATE_PRIVATE_THREADS | SEND_MESSAGES_IN_THREADS",
+    user_cooldown = "30"
+)]
+pub async fn play_bot(
+    ctx: Context<'_>,
+    #[description = "How strong the bot plays. Defaults to Easy."] level: Option<BotLevel>,
+    #[description = "A preset time control. Defaults to 5+0."] time_control: Option<
+        TimeControlPreset,
+    >,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let playing_member: Cow<'_, Member>;
+
+    if let Some(member) = ctx.author_member().await {
+        playing_member = member
+    } else {
+        return Err(anyhow!("Unable to get Member").into());
+    }
+
+    // The member's place is held while the thread is set up, so a second invocation can't start another match.
+    let Ok(reservation) = ctx
+        .data()
+        .match_registry
+        .reserve(playing_member.guild_id, playing_member.user.id)
+    else {
+        ctx.say("You are already in a match. Threadrook currently only supports users competing in a single match at a time per server.").await?;
+        return Ok(());
+    };
+
+    let level = level.unwrap_or(BotLevel::Easy);
+    let time_control =
+        time_control.map_or_else(TimeControl::default, |preset| preset.time_control());
+
+    ctx.say("Creating match...").await?;
+
+    let announcement = format!(
+        "{} is playing a {} chess match against {}!",
+        ctx.author().name,
+        time_control,
+        bot_name(level),
+    );
+    let thread_name = format!(
+        "{} vs {} ({} {})",
+        ctx.author().name,
+        bot_name(level),
+        time_control,
+        time_control.category(),
+    );
+    let (match_thread_message, match_thread) =
+        open_match_thread(ctx, announcement, thread_name).await?;
+
+    let member_colour = if rand::random() {
+        Player::White
+    } else {
+        Player::Black
+    };
+
+    let engine = new_bot_match_engine(playing_member.user.id, member_colour, level, time_control);
+
+    start_match(ctx, engine, match_thread_message, match_thread, reservation).await?;
+
+    Ok(())
+}
+
+/// The engine of a match between a member and a bot. There is nobody to wait for, so it is played as soon as it
+/// opens, which is when its clock starts.
+pub fn new_bot_match_engine(
+    user_id: UserId,
+    member_colour: Player,
+    level: BotLevel,
+    time_control: TimeControl,
+) -> MatchEngine {
+    let start = Board::start_pos();
+
+    MatchEngine::builder()
+        .state(GameState::Playing)
+        .join_deadline(Instant::now())
+        .player_one(
+            ChessPlayer::builder()
+                .seat(Seat::Member(user_id))
+                .player_slot(PlayerSlot::Player1)
+                .in_game_representation(member_colour)
+                .build(),
+        )
+        .player_two(
+            ChessPlayer::builder()
+                .seat(Seat::Bot(level))
+                .player_slot(PlayerSlot::Player2)
+                .in_game_representation(get_opposite_colour(member_colour))
+                .build(),
+        )
+        .clock(ChessClock::starting_with(time_control, start.turn()))
+        .positions(vec![start.zobrist()])
+        .board(start)
+        .rated(false)
+        .build()
+}
@@ -0,0 +1,117 @@
+use pleco::PieceType;
+use poise::serenity_prelude::{
+    self as serenity, InteractionResponseType, MessageComponentInteraction, UserId,
+};
+
+use crate::{Data, Error};
+
+/// The pieces a pawn can promote to, in the order their buttons are shown.
+pub const PROMOTION_PIECES: [PieceType; 4] =
+    [PieceType::Q, PieceType::R, PieceType::B, PieceType::N];
+
+const PROMOTION_BUTTON_PREFIX: &str = "promote";
+
+/// The custom id of the button that promotes to `piece`, e.g. `promote:1234:40:e7e8q`.
+/// `ply` is how many moves had been played when it was offered.
+pub fn promotion_button_id(
+    user_id: UserId,
+    ply: usize,
+    chess_move: &str,
+    piece: PieceType,
+) -> String {
+    format!(
+        "{}:{}:{}:{}{}",
+        PROMOTION_BUTTON_PREFIX,
+        user_id,
+        ply,
+        chess_move,
+        piece.char_lower()
+    )
+}
+
+/// The player a promotion button is meant for, the number of moves it was offered after, and the move it makes.
+pub fn parse_promotion_button_id(custom_id: &str) -> Option<(UserId, usize, String)> {
+    let mut parts = custom_id.split(':');
+    if parts.next()? != PROMOTION_BUTTON_PREFIX {
+        return None;
+    }
+
+    let user_id = UserId(parts.next()?.parse().ok()?);
+    let ply = parts.next()?.parse().ok()?;
+    let chess_move = parts.next()?.to_string();
+
+    Some((user_id, ply, chess_move))
+}
+
+/// Plays the promotion a player picked with one of the buttons the match offered them.
+/// Clicks on anything else are left alone.
+pub async fn handle_promotion_button(
+    ctx: &serenity::Context,
+    component: &MessageComponentInteraction,
+    data: &Data,
+) -> Result<(), Error> {
+    let Some((user_id, ply, chess_move)) = parse_promotion_button_id(&component.data.custom_id)
+    else {
+        return Ok(());
+    };
+
+    if component.user.id != user_id {
+        return reply_privately(
+            ctx,
+            component,
+            format!("These buttons are for <@{}>.", user_id),
+        )
+        .await;
+    }
+
+    let Some(guild_id) = component.guild_id else {
+        return Ok(());
+    };
+
+    let Ok(current) = data
+        .match_registry
+        .pick_promotion(guild_id, user_id, ply, chess_move.clone())
+        .await
+    else {
+        return reply_privately(
+            ctx,
+            component,
+            "You are not playing in a match in this server.",
+        )
+        .await;
+    };
+
+    let content = if current {
+        format!("<@{}> is promoting with {}.", user_id, chess_move)
+    } else {
+        format!(
+            "<@{}>, the game has moved on since this promotion was offered.",
+            user_id
+        )
+    };
+
+    // The buttons are removed so the promotion can't be picked twice.
+    component
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.content(content).components(|c| c))
+        })
+        .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn reply_privately(
+    ctx: &serenity::Context,
+    component: &MessageComponentInteraction,
+    content: impl ToString,
+) -> Result<(), Error> {
+    component
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(content).ephemeral(true))
+        })
+        .await?;
+
+    Ok(())
+}
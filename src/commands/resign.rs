@@ -3,7 +3,7 @@ use std::borrow::Cow;
 use anyhow::anyhow;
 use poise::serenity_prelude::Member;
 
-use crate::{Context, DiscordCommand, DiscordCommunication, Error};
+use crate::{Context, DiscordCommand, Error};
 
 /// Forfeit a chess match.
 #[poise::command(
@@ -24,15 +24,17 @@ pub async fn resign(ctx: Context<'_>) -> Result<(), Error> {
         return Err(anyhow!("Unable to get Member").into());
     }
 
-    ctx.say("Resigning...").await?;
-
     ctx.data()
-        .system_communication_channel
-        .0
-        .send(DiscordCommunication(
+        .match_registry
+        .send_to_member(
             resigning_member.guild_id,
-            DiscordCommand::Resign(Box::new(resigning_member.into_owned())),
-        ))?;
+            resigning_member.user.id,
+            DiscordCommand::Resign(resigning_member.user.id),
+        )
+        .await
+        .map_err(|_| anyhow!("You are not playing in a match in this server."))?;
+
+    ctx.say("Resigning...").await?;
 
     Ok(())
 }
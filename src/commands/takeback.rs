@@ -0,0 +1,133 @@
+use std::borrow::Cow;
+
+use anyhow::anyhow;
+use poise::serenity_prelude::{
+    self as serenity, InteractionResponseType, Member, MessageComponentInteraction, UserId,
+};
+
+use crate::{commands::promotion::reply_privately, Context, Data, DiscordCommand, Error};
+
+const TAKEBACK_BUTTON_PREFIX: &str = "takeback";
+
+/// Ask your opponent to take back your last move.
+#[poise::command(
+    slash_command,
+    required_permissions = "USE_SLASH_COMMANDS",
+    required_bot_permissions = "VIEW_CHANNEL | SEND_MESSAGES | MANAGE_MESSAGES | EMBED_LINKS | READ_MESSAGE_HISTORY | USE_SLASH_COMMANDS | MANAGE_THREADS | CREATE_PUBLIC_THREADS | CREATE_PRIVATE_THREADS | SEND_MESSAGES_IN_THREADS",
+    global_cooldown = "5",
+    ephemeral = "true"
+)]
+pub async fn takeback(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let requesting_member: Cow<'_, Member>;
+
+    if let Some(member) = ctx.author_member().await {
+        requesting_member = member
+    } else {
+        return Err(anyhow!("Unable to get Member").into());
+    }
+
+    ctx.data()
+        .match_registry
+        .send_to_member(
+            requesting_member.guild_id,
+            requesting_member.user.id,
+            DiscordCommand::RequestTakeback(requesting_member.user.id),
+        )
+        .await
+        .map_err(|_| anyhow!("You are not playing in a match in this server."))?;
+
+    ctx.say("Asking for a takeback...").await?;
+
+    Ok(())
+}
+
+/// The custom id of the button that answers a takeback request, e.g. `takeback:1234:40:accept`.
+/// `user_id` is the opponent being asked, and `ply` how many moves had been played when they were asked.
+pub fn takeback_button_id(user_id: UserId, ply: usize, accept: bool) -> String {
+    format!(
+        "{}:{}:{}:{}",
+        TAKEBACK_BUTTON_PREFIX,
+        user_id,
+        ply,
+        if accept { "accept" } else { "decline" }
+    )
+}
+
+/// The player a takeback button is meant for, the number of moves it was offered after, and whether it accepts
+/// the takeback.
+pub fn parse_takeback_button_id(custom_id: &str) -> Option<(UserId, usize, bool)> {
+    let mut parts = custom_id.split(':');
+    if parts.next()? != TAKEBACK_BUTTON_PREFIX {
+        return None;
+    }
+
+    let user_id = UserId(parts.next()?.parse().ok()?);
+    let ply = parts.next()?.parse().ok()?;
+    let accept = match parts.next()? {
+        "accept" => true,
+        "decline" => false,
+        _ => return None,
+    };
+
+    Some((user_id, ply, accept))
+}
+
+/// Answers a takeback request with the button the opponent clicked. Clicks on anything else are left alone.
+pub async fn handle_takeback_button(
+    ctx: &serenity::Context,
+    component: &MessageComponentInteraction,
+    data: &Data,
+) -> Result<(), Error> {
+    let Some((user_id, ply, accept)) = parse_takeback_button_id(&component.data.custom_id) else {
+        return Ok(());
+    };
+
+    if component.user.id != user_id {
+        return reply_privately(
+            ctx,
+            component,
+            format!("These buttons are for <@{}>.", user_id),
+        )
+        .await;
+    }
+
+    let Some(guild_id) = component.guild_id else {
+        return Ok(());
+    };
+
+    let Ok(answered) = data
+        .match_registry
+        .answer_takeback(guild_id, user_id, ply, accept)
+        .await
+    else {
+        return reply_privately(
+            ctx,
+            component,
+            "You are not playing in a match in this server.",
+        )
+        .await;
+    };
+
+    // Only what the match actually did is reported, as the request may have lapsed before the click reached it.
+    let content = if answered {
+        format!(
+            "<@{}> {} the takeback.",
+            user_id,
+            if accept { "accepted" } else { "declined" }
+        )
+    } else {
+        format!("<@{}>, this takeback request is no longer open.", user_id)
+    };
+
+    // The buttons are removed so the request can't be answered twice.
+    component
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.content(content).components(|c| c))
+        })
+        .await?;
+
+    Ok(())
+}
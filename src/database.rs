@@ -0,0 +1,408 @@
+use std::{
+    path::Path,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use pleco::Player;
+use poise::serenity_prelude::{ChannelId, GuildId, MessageId, UserId};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use tokio::time::Instant;
+
+use crate::{
+    board_style::BoardStyle,
+    bot::BotLevel,
+    clock::{ChessClock, ClockMode, TimeControl},
+    engine::{GameState, Seat},
+    match_registry::MatchId,
+    Error,
+};
+
+/// Embedded SQLite storage for matches, so games in progress survive the bot restarting.
+#[derive(Debug)]
+pub struct Database {
+    connection: Mutex<Connection>,
+}
+
+/// Everything needed to rebuild a match and pick its Discord thread back up.
+#[derive(Debug, Clone)]
+pub struct MatchRecord {
+    pub match_id: MatchId,
+    pub guild_id: GuildId,
+    pub parent_channel_id: ChannelId,
+    pub match_thread_message_id: MessageId,
+    pub board_message_id: MessageId,
+    pub time_info_message_id: MessageId,
+    pub legal_move_message_id: MessageId,
+    pub progress: MatchProgress,
+}
+
+/// The part of a match that changes while it is played.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchProgress {
+    pub state: GameState,
+    pub opponent_join_deadline: i32,
+    pub player_one_id: UserId,
+    pub player_one_colour: Player,
+    pub player_two: Seat,
+    /// Stored clocks are stopped. They are started again when the match is picked back up.
+    pub clock: ChessClock,
+    /// The position the match started from, when it isn't the standard one.
+    pub start_fen: Option<String>,
+    pub rated: bool,
+    /// Every move played so far, in order, as pleco UCI strings.
+    pub moves: Vec<String>,
+    /// What the mover had left after each move, for the PGN's clock comments.
+    pub move_clocks: Vec<Duration>,
+}
+
+/// Schema changes, applied in order. `PRAGMA user_version` records how many a database has already had.
+/// Only ever append to this list once the schema has shipped.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS matches (
+        match_id INTEGER PRIMARY KEY,
+        guild_id INTEGER NOT NULL,
+        parent_channel_id INTEGER NOT NULL,
+        match_thread_message_id INTEGER NOT NULL,
+        board_message_id INTEGER NOT NULL,
+        time_info_message_id INTEGER NOT NULL,
+        legal_move_message_id INTEGER NOT NULL,
+        state TEXT NOT NULL,
+        opponent_join_deadline INTEGER NOT NULL,
+        player_one_id INTEGER NOT NULL,
+        player_one_colour TEXT NOT NULL,
+        player_two_id INTEGER,
+        player_two_bot TEXT,
+        base_time INTEGER NOT NULL,
+        increment INTEGER NOT NULL,
+        clock_mode TEXT NOT NULL,
+        white_time_ms INTEGER NOT NULL,
+        black_time_ms INTEGER NOT NULL,
+        turn_time_ms INTEGER NOT NULL,
+        to_move TEXT NOT NULL,
+        start_fen TEXT,
+        rated INTEGER NOT NULL,
+        moves TEXT NOT NULL,
+        move_clocks_ms TEXT NOT NULL
+    );",
+    "CREATE TABLE IF NOT EXISTS guild_settings (
+        guild_id INTEGER PRIMARY KEY,
+        board_style TEXT NOT NULL DEFAULT 'image'
+    );",
+];
+
+impl Database {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// A throwaway database, used when driving matches in tests.
+    pub fn open_in_memory() -> Result<Self, Error> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, Error> {
+        migrate(&connection)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Inserts a match, or overwrites the stored copy if it already exists.
+    pub fn save_match(&self, record: &MatchRecord) -> Result<(), Error> {
+        let progress = &record.progress;
+        let now = Instant::now();
+
+        self.lock().execute(
+            "INSERT OR REPLACE INTO matches (
+                match_id, guild_id, parent_channel_id, match_thread_message_id, board_message_id,
+                time_info_message_id, legal_move_message_id, state, opponent_join_deadline,
+                player_one_id, player_one_colour, player_two_id, player_two_bot, base_time, increment,
+                clock_mode, white_time_ms, black_time_ms, turn_time_ms, to_move, start_fen, rated, moves,
+                move_clocks_ms
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24)",
+            params![
+                to_sql_id(record.match_id.0),
+                to_sql_id(record.guild_id.0),
+                to_sql_id(record.parent_channel_id.0),
+                to_sql_id(record.match_thread_message_id.0),
+                to_sql_id(record.board_message_id.0),
+                to_sql_id(record.time_info_message_id.0),
+                to_sql_id(record.legal_move_message_id.0),
+                game_state_to_str(progress.state),
+                progress.opponent_join_deadline,
+                to_sql_id(progress.player_one_id.0),
+                player_to_str(progress.player_one_colour),
+                progress.player_two.user_id().map(|id| to_sql_id(id.0)),
+                progress.player_two.bot().map(bot_level_to_str),
+                progress.clock.time_control().base,
+                progress.clock.time_control().increment,
+                clock_mode_to_str(progress.clock.time_control().mode),
+                to_sql_millis(progress.clock.remaining_at_turn_start(Player::White)),
+                to_sql_millis(progress.clock.remaining_at_turn_start(Player::Black)),
+                to_sql_millis(progress.clock.turn_time(now)),
+                player_to_str(progress.clock.to_move()),
+                progress.start_fen,
+                progress.rated,
+                progress.moves.join(" "),
+                join_millis(&progress.move_clocks),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Updates the progress of a stored match. Matches that were never stored are left alone.
+    pub fn save_progress(&self, match_id: MatchId, progress: &MatchProgress) -> Result<(), Error> {
+        let now = Instant::now();
+
+        self.lock().execute(
+            "UPDATE matches SET
+                state = ?2, opponent_join_deadline = ?3, player_one_id = ?4, player_one_colour = ?5,
+                player_two_id = ?6, player_two_bot = ?7, white_time_ms = ?8, black_time_ms = ?9,
+                turn_time_ms = ?10, to_move = ?11, moves = ?12, move_clocks_ms = ?13
+            WHERE match_id = ?1",
+            params![
+                to_sql_id(match_id.0),
+                game_state_to_str(progress.state),
+                progress.opponent_join_deadline,
+                to_sql_id(progress.player_one_id.0),
+                player_to_str(progress.player_one_colour),
+                progress.player_two.user_id().map(|id| to_sql_id(id.0)),
+                progress.player_two.bot().map(bot_level_to_str),
+                to_sql_millis(progress.clock.remaining_at_turn_start(Player::White)),
+                to_sql_millis(progress.clock.remaining_at_turn_start(Player::Black)),
+                to_sql_millis(progress.clock.turn_time(now)),
+                player_to_str(progress.clock.to_move()),
+                progress.moves.join(" "),
+                join_millis(&progress.move_clocks),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn delete_match(&self, match_id: MatchId) -> Result<(), Error> {
+        self.lock().execute(
+            "DELETE FROM matches WHERE match_id = ?1",
+            params![to_sql_id(match_id.0)],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn load_matches(&self) -> Result<Vec<MatchRecord>, Error> {
+        let connection = self.lock();
+        let mut statement = connection.prepare("SELECT * FROM matches")?;
+        let rows = statement.query_map([], |row| Ok(read_record(row)))?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            records.push(row??);
+        }
+
+        Ok(records)
+    }
+
+    /// How boards are shown in a guild. Guilds that never chose get images.
+    pub fn board_style(&self, guild_id: GuildId) -> Result<BoardStyle, Error> {
+        let style: Option<String> = self
+            .lock()
+            .query_row(
+                "SELECT board_style FROM guild_settings WHERE guild_id = ?1",
+                params![to_sql_id(guild_id.0)],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        style.map_or(Ok(BoardStyle::default()), |style| {
+            board_style_from_str(&style)
+        })
+    }
+
+    pub fn set_board_style(&self, guild_id: GuildId, style: BoardStyle) -> Result<(), Error> {
+        self.lock().execute(
+            "INSERT INTO guild_settings (guild_id, board_style) VALUES (?1, ?2)
+            ON CONFLICT (guild_id) DO UPDATE SET board_style = excluded.board_style",
+            params![to_sql_id(guild_id.0), board_style_to_str(style)],
+        )?;
+
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn migrate(connection: &Connection) -> Result<(), Error> {
+    let applied: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        connection.execute_batch(&format!(
+            "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+            migration,
+            version + 1
+        ))?;
+    }
+
+    Ok(())
+}
+
+fn read_record(row: &Row) -> Result<MatchRecord, Error> {
+    let moves: String = row.get("moves")?;
+    let move_clocks: String = row.get("move_clocks_ms")?;
+    let player_two_id: Option<i64> = row.get("player_two_id")?;
+    let player_two_bot: Option<String> = row.get("player_two_bot")?;
+    let player_two = match (player_two_bot, player_two_id) {
+        (Some(level), _) => Seat::Bot(bot_level_from_str(&level)?),
+        (None, Some(id)) => Seat::Member(UserId(from_sql_id(id))),
+        (None, None) => Seat::Open,
+    };
+
+    Ok(MatchRecord {
+        match_id: ChannelId(from_sql_id(row.get("match_id")?)),
+        guild_id: GuildId(from_sql_id(row.get("guild_id")?)),
+        parent_channel_id: ChannelId(from_sql_id(row.get("parent_channel_id")?)),
+        match_thread_message_id: MessageId(from_sql_id(row.get("match_thread_message_id")?)),
+        board_message_id: MessageId(from_sql_id(row.get("board_message_id")?)),
+        time_info_message_id: MessageId(from_sql_id(row.get("time_info_message_id")?)),
+        legal_move_message_id: MessageId(from_sql_id(row.get("legal_move_message_id")?)),
+        progress: MatchProgress {
+            state: game_state_from_str(&row.get::<_, String>("state")?)?,
+            opponent_join_deadline: row.get("opponent_join_deadline")?,
+            player_one_id: UserId(from_sql_id(row.get("player_one_id")?)),
+            player_one_colour: player_from_str(&row.get::<_, String>("player_one_colour")?)?,
+            player_two,
+            clock: ChessClock::builder()
+                .time_control(
+                    TimeControl::new(row.get("base_time")?, row.get("increment")?)
+                        .with_mode(clock_mode_from_str(&row.get::<_, String>("clock_mode")?)?),
+                )
+                .white(from_sql_millis(row.get("white_time_ms")?))
+                .black(from_sql_millis(row.get("black_time_ms")?))
+                .turn_time(from_sql_millis(row.get("turn_time_ms")?))
+                .to_move(player_from_str(&row.get::<_, String>("to_move")?)?)
+                .build(),
+            start_fen: row.get("start_fen")?,
+            rated: row.get("rated")?,
+            moves: moves.split_whitespace().map(str::to_string).collect(),
+            move_clocks: move_clocks
+                .split_whitespace()
+                .map(|millis| Ok(from_sql_millis(millis.parse()?)))
+                .collect::<Result<_, Error>>()?,
+        },
+    })
+}
+
+// SQLite only has signed 64 bit integers. Discord snowflakes fit in 63 bits, so this round trips losslessly.
+fn to_sql_id(id: u64) -> i64 {
+    id as i64
+}
+
+fn from_sql_id(id: i64) -> u64 {
+    id as u64
+}
+
+fn to_sql_millis(duration: Duration) -> i64 {
+    duration.as_millis() as i64
+}
+
+fn from_sql_millis(millis: i64) -> Duration {
+    Duration::from_millis(millis.max(0) as u64)
+}
+
+fn join_millis(durations: &[Duration]) -> String {
+    durations
+        .iter()
+        .map(|duration| to_sql_millis(*duration).to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn game_state_to_str(state: GameState) -> &'static str {
+    match state {
+        GameState::WaitingForOpponent => "waiting_for_opponent",
+        GameState::Playing => "playing",
+        GameState::GameOver => "game_over",
+    }
+}
+
+fn game_state_from_str(state: &str) -> Result<GameState, Error> {
+    match state {
+        "waiting_for_opponent" => Ok(GameState::WaitingForOpponent),
+        "playing" => Ok(GameState::Playing),
+        "game_over" => Ok(GameState::GameOver),
+        _ => Err(anyhow!("Unknown game state '{}' in the database.", state).into()),
+    }
+}
+
+fn player_to_str(player: Player) -> &'static str {
+    match player {
+        Player::White => "white",
+        Player::Black => "black",
+    }
+}
+
+fn player_from_str(player: &str) -> Result<Player, Error> {
+    match player {
+        "white" => Ok(Player::White),
+        "black" => Ok(Player::Black),
+        _ => Err(anyhow!("Unknown player colour '{}' in the database.", player).into()),
+    }
+}
+
+fn clock_mode_to_str(mode: ClockMode) -> &'static str {
+    match mode {
+        ClockMode::Fischer => "fischer",
+        ClockMode::UsDelay => "us_delay",
+        ClockMode::Bronstein => "bronstein",
+    }
+}
+
+fn clock_mode_from_str(mode: &str) -> Result<ClockMode, Error> {
+    match mode {
+        "fischer" => Ok(ClockMode::Fischer),
+        "us_delay" => Ok(ClockMode::UsDelay),
+        "bronstein" => Ok(ClockMode::Bronstein),
+        _ => Err(anyhow!("Unknown clock mode '{}' in the database.", mode).into()),
+    }
+}
+
+fn board_style_to_str(style: BoardStyle) -> &'static str {
+    match style {
+        BoardStyle::Image => "image",
+        BoardStyle::Unicode => "unicode",
+        BoardStyle::Emoji => "emoji",
+    }
+}
+
+fn board_style_from_str(style: &str) -> Result<BoardStyle, Error> {
+    match style {
+        "image" => Ok(BoardStyle::Image),
+        "unicode" => Ok(BoardStyle::Unicode),
+        "emoji" => Ok(BoardStyle::Emoji),
+        _ => Err(anyhow!("Unknown board style '{}' in the database.", style).into()),
+    }
+}
+
+fn bot_level_to_str(level: BotLevel) -> &'static str {
+    match level {
+        BotLevel::Easy => "easy",
+        BotLevel::Medium => "medium",
+        BotLevel::Hard => "hard",
+    }
+}
+
+fn bot_level_from_str(level: &str) -> Result<BotLevel, Error> {
+    match level {
+        "easy" => Ok(BotLevel::Easy),
+        "medium" => Ok(BotLevel::Medium),
+        "hard" => Ok(BotLevel::Hard),
+        _ => Err(anyhow!("Unknown bot level '{}' in the database.", level).into()),
+    }
+}
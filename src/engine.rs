@@ -0,0 +1,907 @@
+use std::time::Duration;
+
+use pleco::{BitBoard, Board, PieceType, Player};
+use poise::serenity_prelude::UserId;
+use tokio::time::Instant;
+
+use crate::{
+    bot::BotLevel,
+    clock::{ChessClock, TimeControl},
+    notation::{
+        diagnose_illegal_move, parse_move, to_numbered_san, to_san, IllegalMoveReason, LegalMove,
+        MoveInputError,
+    },
+};
+
+/// The rules, turn handling and clocks of a single match, with no knowledge of how the match is displayed.
+///
+/// Every input returns the `MatchEvent`s it caused, which a `MatchFrontend` then renders.
+#[derive(Debug, Clone, buildstructor::Builder)]
+pub struct MatchEngine {
+    state: GameState,
+    /// When the match is aborted if nobody has joined it.
+    join_deadline: Instant,
+    player_one: ChessPlayer,
+    player_two: ChessPlayer,
+    clock: ChessClock,
+    result: Option<GameResult>,
+    /// An open draw offer, if there is one.
+    draw_offer: Option<DrawOffer>,
+    /// An open takeback request, if there is one.
+    takeback_request: Option<TakebackRequest>,
+    board: Board,
+    /// The position the match started from, when it isn't the standard one.
+    start_fen: Option<String>,
+    /// Rated matches are played as they fall, so they don't allow takebacks.
+    rated: bool,
+    /// Every move played so far, as pleco UCI strings.
+    moves: Vec<String>,
+    /// The zobrist hash of every position reached so far, starting with the initial one.
+    positions: Vec<u64>,
+    /// What the mover had left on their clock after each move, one for every entry in `moves`.
+    move_clocks: Vec<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameState {
+    WaitingForOpponent,
+    Playing,
+    GameOver,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameResult {
+    Winner(Player),
+    Draw,
+    Aborted,
+}
+
+/// A copy of the board, along with the colour the user it was made for plays, if they play in the match.
+#[derive(Debug, Clone)]
+pub struct BoardView {
+    pub board: Board,
+    pub colour: Option<Player>,
+}
+
+/// Why a match ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEndReason {
+    Checkmate,
+    Stalemate,
+    /// The colour that resigned.
+    Resignation(Player),
+    /// The colour whose time ran out.
+    Timeout(Player),
+    /// The colour whose time ran out, against an opponent who couldn't have checkmated them.
+    TimeoutVsInsufficientMaterial(Player),
+    /// Both players agreed to a draw.
+    Agreement,
+    InsufficientMaterial,
+    FivefoldRepetition,
+    /// 75 moves by each side without a capture or pawn move.
+    SeventyFiveMoveRule,
+    /// Claimed by a player once the same position occurred three times.
+    ThreefoldRepetition,
+    /// Claimed by a player after 50 moves by each side without a capture or pawn move.
+    FiftyMoveRule,
+    /// The creator withdrew the match before anyone joined.
+    Cancelled,
+    NoOpponent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, buildstructor::Builder)]
+pub struct ChessPlayer {
+    pub seat: Seat,
+    player_slot: PlayerSlot,
+    in_game_representation: Player,
+}
+
+/// Who plays a side of the match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Seat {
+    /// Nobody has joined yet.
+    Open,
+    Member(UserId),
+    /// One of the built-in bots, which has no Discord member behind it.
+    Bot(BotLevel),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlayerSlot {
+    Player1,
+    Player2,
+}
+
+/// A draw offer that the opponent hasn't answered yet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawOffer {
+    /// The colour that offered the draw.
+    pub offered_by: Player,
+    /// The offer lapses once this many moves have been played, which is when the offering side has moved again.
+    lapses_at: usize,
+}
+
+/// A takeback that the opponent hasn't answered yet. It is withdrawn as soon as another move is played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TakebackRequest {
+    /// The colour that asked for the takeback.
+    pub requested_by: Player,
+    /// How many moves would be taken back: the asking side's last move, and the reply to it if there was one.
+    pub moves: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchEvent {
+    OpponentJoined(UserId),
+    /// Someone tried to join after the match already had two players.
+    JoinRejected(UserId),
+    GameStarted,
+    MoveApplied {
+        player: ChessPlayer,
+        /// The move in UCI, as it is stored.
+        chess_move: String,
+        /// The move in SAN after its move number, e.g. `12... Nxe4+`.
+        san: String,
+    },
+    IllegalMove {
+        chess_move: String,
+        reason: IllegalMoveReason,
+    },
+    /// A pawn is moving to the last rank without saying what to promote to, given here in UCI without the piece.
+    PromotionNeeded {
+        player: ChessPlayer,
+        chess_move: String,
+    },
+    /// The move isn't written in any notation the bot understands.
+    UnreadableMove(String),
+    /// The move fits more than one legal move, given here in SAN.
+    AmbiguousMove {
+        chess_move: String,
+        candidates: Vec<String>,
+    },
+    NotAPlayer(UserId),
+    DrawOffered(ChessPlayer),
+    DrawDeclined(ChessPlayer),
+    /// The player who offered a draw moved again before it was answered.
+    DrawOfferLapsed(ChessPlayer),
+    /// Someone tried to answer a draw offer that their opponent never made.
+    NoDrawOffer(UserId),
+    /// Someone claimed a draw, but neither threefold repetition nor the fifty-move rule applies.
+    DrawClaimRejected(UserId),
+    TakebackRequested {
+        player: ChessPlayer,
+        moves: usize,
+    },
+    /// The opponent of `player` agreed to take back their last `moves` moves.
+    TakebackAccepted {
+        player: ChessPlayer,
+        moves: usize,
+    },
+    TakebackDeclined(ChessPlayer),
+    /// Someone asked for a takeback before making a move of their own.
+    NothingToTakeBack(UserId),
+    /// Someone asked for a takeback in a rated match.
+    TakebacksNotAllowed(UserId),
+    /// Someone tried to answer a takeback request that their opponent never made.
+    NoTakebackRequest(UserId),
+    /// A premove couldn't be played once the opponent had moved, so it was thrown away.
+    PremoveDropped {
+        user_id: UserId,
+        chess_move: String,
+    },
+    GameEnded {
+        result: GameResult,
+        reason: GameEndReason,
+    },
+}
+
+impl MatchEngine {
+    pub fn join(&mut self, user_id: UserId, now: Instant) -> Vec<MatchEvent> {
+        if self.state != GameState::WaitingForOpponent {
+            return vec![MatchEvent::JoinRejected(user_id)];
+        }
+
+        self.player_two.seat = Seat::Member(user_id);
+        self.state = GameState::Playing;
+        self.clock.start(now);
+
+        vec![MatchEvent::OpponentJoined(user_id), MatchEvent::GameStarted]
+    }
+
+    /// Starts the clock of a match that is played from the moment it opens.
+    pub fn start_clock(&mut self, now: Instant) {
+        self.clock.start(now);
+    }
+
+    pub fn make_move(
+        &mut self,
+        user_id: UserId,
+        chess_move: &str,
+        now: Instant,
+    ) -> Vec<MatchEvent> {
+        if self.state != GameState::Playing {
+            return Vec::new();
+        }
+
+        // The flag may have fallen before the match got around to noticing.
+        let expired = self.expire(now);
+        if !expired.is_empty() {
+            return expired;
+        }
+
+        let Some(query_player) = self.player_of(user_id) else {
+            return vec![MatchEvent::NotAPlayer(user_id)];
+        };
+
+        self.play(query_player, chess_move, now)
+    }
+
+    /// Plays the move a bot chose, if a bot is to move.
+    pub fn make_bot_move(&mut self, chess_move: &str, now: Instant) -> Vec<MatchEvent> {
+        if self.bot_to_move().is_none() {
+            return Vec::new();
+        }
+
+        let expired = self.expire(now);
+        if !expired.is_empty() {
+            return expired;
+        }
+
+        self.play(
+            self.player_with_colour(self.acting_colour()),
+            chess_move,
+            now,
+        )
+    }
+
+    /// Checks and plays a move for `query_player`, whoever moved it.
+    fn play(
+        &mut self,
+        query_player: ChessPlayer,
+        chess_move: &str,
+        now: Instant,
+    ) -> Vec<MatchEvent> {
+        if query_player.in_game_representation != self.acting_colour() {
+            return vec![MatchEvent::IllegalMove {
+                chess_move: chess_move.to_string(),
+                reason: IllegalMoveReason::NotYourTurn,
+            }];
+        }
+
+        let bit_move = match parse_move(&self.board, chess_move) {
+            Ok(bit_move) => bit_move,
+            Err(MoveInputError::Unreadable) => {
+                return vec![MatchEvent::UnreadableMove(chess_move.to_string())]
+            }
+            Err(MoveInputError::Illegal) => {
+                return match diagnose_illegal_move(&self.board, chess_move) {
+                    Some(reason) => vec![MatchEvent::IllegalMove {
+                        chess_move: chess_move.to_string(),
+                        reason,
+                    }],
+                    None => vec![MatchEvent::UnreadableMove(chess_move.to_string())],
+                }
+            }
+            Err(MoveInputError::MissingPromotion { from, to }) => {
+                // Nothing is played until the player picks a piece, and their clock keeps running meanwhile.
+                return vec![MatchEvent::PromotionNeeded {
+                    player: query_player,
+                    chess_move: format!("{}{}", from, to),
+                }];
+            }
+            Err(MoveInputError::Ambiguous(candidates)) => {
+                return vec![MatchEvent::AmbiguousMove {
+                    chess_move: chess_move.to_string(),
+                    candidates: candidates
+                        .into_iter()
+                        .map(|candidate| to_san(&self.board, candidate))
+                        .collect(),
+                }]
+            }
+        };
+
+        let san = to_numbered_san(&self.board, bit_move);
+        self.board.apply_move(bit_move);
+        self.moves.push(bit_move.stringify());
+        self.positions.push(self.board.zobrist());
+
+        self.clock.press(now);
+        self.takeback_request = None;
+        self.move_clocks.push(
+            self.clock
+                .remaining_at_turn_start(query_player.in_game_representation),
+        );
+
+        let mut events = vec![MatchEvent::MoveApplied {
+            player: query_player,
+            chess_move: bit_move.stringify(),
+            san,
+        }];
+
+        if let Some(draw_offer) = self.draw_offer {
+            if self.moves.len() >= draw_offer.lapses_at {
+                self.draw_offer = None;
+                events.push(MatchEvent::DrawOfferLapsed(
+                    self.player_with_colour(draw_offer.offered_by),
+                ));
+            }
+        }
+
+        if self.board.checkmate() {
+            events.push(self.end(
+                GameResult::Winner(query_player.in_game_representation),
+                GameEndReason::Checkmate,
+            ));
+        } else if let Some(reason) = self.automatic_draw() {
+            events.push(self.end(GameResult::Draw, reason));
+        }
+
+        events
+    }
+
+    /// Draws that end the game without anyone claiming them.
+    fn automatic_draw(&self) -> Option<GameEndReason> {
+        // pleco's `stalemate()` also counts 25 moves without a capture or pawn move, so it isn't used here.
+        if !self.board.in_check() && self.board.generate_moves().is_empty() {
+            Some(GameEndReason::Stalemate)
+        } else if insufficient_material(&self.board) {
+            Some(GameEndReason::InsufficientMaterial)
+        } else if self.repetitions() >= 5 {
+            Some(GameEndReason::FivefoldRepetition)
+        } else if self.board.rule_50() >= 150 {
+            Some(GameEndReason::SeventyFiveMoveRule)
+        } else {
+            None
+        }
+    }
+
+    /// Draws a player may claim, if any applies to the current position.
+    pub fn claimable_draw(&self) -> Option<GameEndReason> {
+        if self.repetitions() >= 3 {
+            Some(GameEndReason::ThreefoldRepetition)
+        } else if self.board.rule_50() >= 100 {
+            Some(GameEndReason::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    /// How many times the current position has occurred, counting this time.
+    fn repetitions(&self) -> usize {
+        let current = self.board.zobrist();
+
+        // A position can't repeat across a capture or pawn move, so only those since the last one are compared.
+        self.positions
+            .iter()
+            .rev()
+            .take(self.board.rule_50().max(0) as usize + 1)
+            .filter(|position| **position == current)
+            .count()
+    }
+
+    pub fn resign(&mut self, user_id: UserId) -> Vec<MatchEvent> {
+        match self.state {
+            GameState::WaitingForOpponent if self.player_one.user_id() == Some(user_id) => {
+                // Nobody has joined yet, so resigning simply withdraws the open match.
+                vec![self.end(GameResult::Aborted, GameEndReason::Cancelled)]
+            }
+            GameState::Playing => match self.player_of(user_id) {
+                Some(query_player) => {
+                    let resigning_colour = query_player.in_game_representation;
+                    vec![self.end(
+                        GameResult::Winner(get_opposite_colour(resigning_colour)),
+                        GameEndReason::Resignation(resigning_colour),
+                    )]
+                }
+                None => vec![MatchEvent::NotAPlayer(user_id)],
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// Offers the opponent a draw. If they had already offered one, the draw is agreed instead.
+    pub fn offer_draw(&mut self, user_id: UserId) -> Vec<MatchEvent> {
+        if self.state != GameState::Playing {
+            return Vec::new();
+        }
+
+        let Some(query_player) = self.player_of(user_id) else {
+            return vec![MatchEvent::NotAPlayer(user_id)];
+        };
+
+        match self.draw_offer {
+            Some(draw_offer) if draw_offer.offered_by != query_player.in_game_representation => {
+                self.accept_draw(user_id)
+            }
+            // The offer is already open.
+            Some(_) => Vec::new(),
+            None if self.opponent_of(query_player).bot().is_some() => {
+                // Bots play every game out.
+                vec![
+                    MatchEvent::DrawOffered(query_player),
+                    MatchEvent::DrawDeclined(self.opponent_of(query_player)),
+                ]
+            }
+            None => {
+                // An offer made before moving goes along with that move, so it lapses after the one after it.
+                let offering_side_moves_next =
+                    self.acting_colour() == query_player.in_game_representation;
+                self.draw_offer = Some(DrawOffer {
+                    offered_by: query_player.in_game_representation,
+                    lapses_at: self.moves.len() + if offering_side_moves_next { 3 } else { 2 },
+                });
+
+                vec![MatchEvent::DrawOffered(query_player)]
+            }
+        }
+    }
+
+    pub fn accept_draw(&mut self, user_id: UserId) -> Vec<MatchEvent> {
+        if self.state != GameState::Playing {
+            return Vec::new();
+        }
+
+        let Some(query_player) = self.player_of(user_id) else {
+            return vec![MatchEvent::NotAPlayer(user_id)];
+        };
+
+        match self.draw_offer {
+            Some(draw_offer) if draw_offer.offered_by != query_player.in_game_representation => {
+                self.draw_offer = None;
+                vec![self.end(GameResult::Draw, GameEndReason::Agreement)]
+            }
+            _ => vec![MatchEvent::NoDrawOffer(user_id)],
+        }
+    }
+
+    pub fn decline_draw(&mut self, user_id: UserId) -> Vec<MatchEvent> {
+        if self.state != GameState::Playing {
+            return Vec::new();
+        }
+
+        let Some(query_player) = self.player_of(user_id) else {
+            return vec![MatchEvent::NotAPlayer(user_id)];
+        };
+
+        match self.draw_offer {
+            Some(draw_offer) if draw_offer.offered_by != query_player.in_game_representation => {
+                self.draw_offer = None;
+                vec![MatchEvent::DrawDeclined(query_player)]
+            }
+            _ => vec![MatchEvent::NoDrawOffer(user_id)],
+        }
+    }
+
+    /// Asks the opponent to take back the player's last move, along with the reply to it if there was one.
+    /// If the opponent had already asked for a takeback, theirs is accepted instead.
+    pub fn request_takeback(&mut self, user_id: UserId, now: Instant) -> Vec<MatchEvent> {
+        if self.state != GameState::Playing {
+            return Vec::new();
+        }
+
+        let Some(query_player) = self.player_of(user_id) else {
+            return vec![MatchEvent::NotAPlayer(user_id)];
+        };
+        if self.rated {
+            return vec![MatchEvent::TakebacksNotAllowed(user_id)];
+        }
+
+        match self.takeback_request {
+            Some(request) if request.requested_by != query_player.in_game_representation => {
+                return self.accept_takeback(user_id, now);
+            }
+            // The request is already open.
+            Some(_) => return Vec::new(),
+            None => {}
+        }
+
+        let moves = if self.acting_colour() == query_player.in_game_representation {
+            2
+        } else {
+            1
+        };
+        if moves > self.moves.len() {
+            return vec![MatchEvent::NothingToTakeBack(user_id)];
+        }
+
+        let request = TakebackRequest {
+            requested_by: query_player.in_game_representation,
+            moves,
+        };
+
+        if self.opponent_of(query_player).bot().is_some() {
+            // Bots never mind taking a move back.
+            let expired = self.expire(now);
+            if !expired.is_empty() {
+                return expired;
+            }
+
+            return self.take_back(request, now);
+        }
+
+        self.takeback_request = Some(request);
+
+        vec![MatchEvent::TakebackRequested {
+            player: query_player,
+            moves,
+        }]
+    }
+
+    /// Takes back the moves the opponent asked for, and sets both clocks back to how they read when the asking
+    /// side's turn started. That turn starts again at `now`.
+    pub fn accept_takeback(&mut self, user_id: UserId, now: Instant) -> Vec<MatchEvent> {
+        if self.state != GameState::Playing {
+            return Vec::new();
+        }
+
+        let expired = self.expire(now);
+        if !expired.is_empty() {
+            return expired;
+        }
+
+        let Some(query_player) = self.player_of(user_id) else {
+            return vec![MatchEvent::NotAPlayer(user_id)];
+        };
+
+        let request = match self.takeback_request {
+            Some(request) if request.requested_by != query_player.in_game_representation => request,
+            _ => return vec![MatchEvent::NoTakebackRequest(user_id)],
+        };
+
+        self.take_back(request, now)
+    }
+
+    fn take_back(&mut self, request: TakebackRequest, now: Instant) -> Vec<MatchEvent> {
+        for _ in 0..request.moves {
+            self.board.undo_move();
+            self.moves.pop();
+            self.positions.pop();
+            self.move_clocks.pop();
+        }
+
+        let base = Duration::from_secs(self.time_control().base.max(0) as u64);
+        let white = self.last_move_clock(Player::White).unwrap_or(base);
+        let black = self.last_move_clock(Player::Black).unwrap_or(base);
+        self.clock.set_back(white, black, self.acting_colour(), now);
+
+        self.takeback_request = None;
+        self.draw_offer = None;
+
+        vec![MatchEvent::TakebackAccepted {
+            player: self.player_with_colour(request.requested_by),
+            moves: request.moves,
+        }]
+    }
+
+    pub fn decline_takeback(&mut self, user_id: UserId) -> Vec<MatchEvent> {
+        if self.state != GameState::Playing {
+            return Vec::new();
+        }
+
+        let Some(query_player) = self.player_of(user_id) else {
+            return vec![MatchEvent::NotAPlayer(user_id)];
+        };
+
+        match self.takeback_request {
+            Some(request) if request.requested_by != query_player.in_game_representation => {
+                self.takeback_request = None;
+                vec![MatchEvent::TakebackDeclined(query_player)]
+            }
+            _ => vec![MatchEvent::NoTakebackRequest(user_id)],
+        }
+    }
+
+    /// What `colour` had left after their last move, if they have moved.
+    fn last_move_clock(&self, colour: Player) -> Option<Duration> {
+        // Moves alternate, so the last move was made by the side not to move, the one before by the side to move.
+        let last_by_colour = if colour == self.acting_colour() { 2 } else { 1 };
+
+        self.move_clocks
+            .len()
+            .checked_sub(last_by_colour)
+            .map(|index| self.move_clocks[index])
+    }
+
+    /// Ends the match in a draw, if threefold repetition or the fifty-move rule applies.
+    pub fn claim_draw(&mut self, user_id: UserId) -> Vec<MatchEvent> {
+        if self.state != GameState::Playing {
+            return Vec::new();
+        }
+
+        if self.player_of(user_id).is_none() {
+            return vec![MatchEvent::NotAPlayer(user_id)];
+        }
+
+        match self.claimable_draw() {
+            Some(reason) => vec![self.end(GameResult::Draw, reason)],
+            None => vec![MatchEvent::DrawClaimRejected(user_id)],
+        }
+    }
+
+    /// Ends the match if its `deadline` has passed.
+    pub fn expire(&mut self, now: Instant) -> Vec<MatchEvent> {
+        match self.state {
+            GameState::WaitingForOpponent if now >= self.join_deadline => {
+                vec![self.end(GameResult::Aborted, GameEndReason::NoOpponent)]
+            }
+            GameState::Playing if self.clock.is_flagged(self.acting_colour(), now) => {
+                let acting_colour = self.acting_colour();
+                let opponent_colour = get_opposite_colour(acting_colour);
+
+                if has_mating_material(&self.board, opponent_colour) {
+                    vec![self.end(
+                        GameResult::Winner(opponent_colour),
+                        GameEndReason::Timeout(acting_colour),
+                    )]
+                } else {
+                    vec![self.end(
+                        GameResult::Draw,
+                        GameEndReason::TimeoutVsInsufficientMaterial(acting_colour),
+                    )]
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The next moment the match might end on its own: the join deadline, or the running clock's flag falling.
+    pub fn deadline(&self) -> Option<Instant> {
+        match self.state {
+            GameState::WaitingForOpponent => Some(self.join_deadline),
+            GameState::Playing => self.clock.flag_deadline(),
+            GameState::GameOver => None,
+        }
+    }
+
+    fn end(&mut self, result: GameResult, reason: GameEndReason) -> MatchEvent {
+        self.state = GameState::GameOver;
+        self.result = Some(result);
+
+        MatchEvent::GameEnded { result, reason }
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    pub fn draw_offer(&self) -> Option<DrawOffer> {
+        self.draw_offer
+    }
+
+    pub fn takeback_request(&self) -> Option<TakebackRequest> {
+        self.takeback_request
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn start_fen(&self) -> Option<&str> {
+        self.start_fen.as_deref()
+    }
+
+    pub fn rated(&self) -> bool {
+        self.rated
+    }
+
+    /// The position the moves were played from.
+    pub fn start_board(&self) -> Board {
+        self.start_fen
+            .as_deref()
+            .and_then(|fen| Board::from_fen(fen).ok())
+            .unwrap_or_else(Board::start_pos)
+    }
+
+    pub fn moves(&self) -> &[String] {
+        &self.moves
+    }
+
+    pub fn move_clocks(&self) -> &[Duration] {
+        &self.move_clocks
+    }
+
+    pub fn join_deadline(&self) -> Instant {
+        self.join_deadline
+    }
+
+    /// Whole seconds left for an opponent to join, rounded up.
+    pub fn opponent_join_deadline(&self, now: Instant) -> i32 {
+        let left = self.join_deadline.saturating_duration_since(now);
+
+        (left + Duration::from_millis(999)).as_secs() as i32
+    }
+
+    pub fn player_one(&self) -> ChessPlayer {
+        self.player_one
+    }
+
+    pub fn player_two(&self) -> ChessPlayer {
+        self.player_two
+    }
+
+    pub fn clock(&self) -> &ChessClock {
+        &self.clock
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        self.clock.time_control()
+    }
+
+    pub fn acting_colour(&self) -> Player {
+        self.board.turn()
+    }
+
+    pub fn get_colour_timeleft(&self, query_colour: Player, now: Instant) -> Duration {
+        self.clock.remaining(query_colour, now)
+    }
+
+    pub fn legal_moves(&self) -> Vec<LegalMove> {
+        self.board
+            .generate_moves()
+            .iter()
+            .map(|chess_move| LegalMove {
+                san: to_san(&self.board, *chess_move),
+                uci: chess_move.stringify(),
+            })
+            .collect()
+    }
+
+    /// The moves a user could make right now. Empty unless they are playing and it is their turn.
+    pub fn legal_moves_for(&self, user_id: UserId) -> Vec<LegalMove> {
+        match self.player_of(user_id) {
+            Some(player)
+                if self.state == GameState::Playing
+                    && player.in_game_representation == self.acting_colour() =>
+            {
+                self.legal_moves()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether the user plays in the match and is waiting for their opponent to move.
+    pub fn is_waiting(&self, user_id: UserId) -> bool {
+        self.state == GameState::Playing
+            && match self.player_of(user_id) {
+                Some(player) => player.in_game_representation != self.acting_colour(),
+                None => false,
+            }
+    }
+
+    /// The bot that is to move, if one is.
+    pub fn bot_to_move(&self) -> Option<BotLevel> {
+        if self.state != GameState::Playing {
+            return None;
+        }
+
+        self.player_with_colour(self.acting_colour()).bot()
+    }
+
+    /// The player of the given colour.
+    pub fn player_with_colour(&self, colour: Player) -> ChessPlayer {
+        if self.player_one.in_game_representation == colour {
+            self.player_one
+        } else {
+            self.player_two
+        }
+    }
+
+    /// A copy of the board, for showing to a user.
+    pub fn board_view(&self, user_id: UserId) -> BoardView {
+        BoardView {
+            board: self.board.clone(),
+            colour: self.player_of(user_id).map(|player| player.colour()),
+        }
+    }
+
+    fn player_of(&self, user_id: UserId) -> Option<ChessPlayer> {
+        [self.player_one, self.player_two]
+            .into_iter()
+            .find(|player| player.user_id() == Some(user_id))
+    }
+
+    fn opponent_of(&self, player: ChessPlayer) -> ChessPlayer {
+        self.player_with_colour(get_opposite_colour(player.in_game_representation))
+    }
+}
+
+impl ChessPlayer {
+    pub fn colour(&self) -> Player {
+        self.in_game_representation
+    }
+
+    pub fn user_id(&self) -> Option<UserId> {
+        self.seat.user_id()
+    }
+
+    pub fn bot(&self) -> Option<BotLevel> {
+        self.seat.bot()
+    }
+}
+
+impl Seat {
+    /// The member in this seat, unless it is still open or a bot's.
+    pub fn user_id(&self) -> Option<UserId> {
+        match self {
+            Seat::Member(user_id) => Some(*user_id),
+            Seat::Open | Seat::Bot(_) => None,
+        }
+    }
+
+    pub fn bot(&self) -> Option<BotLevel> {
+        match self {
+            Seat::Bot(level) => Some(*level),
+            Seat::Open | Seat::Member(_) => None,
+        }
+    }
+}
+
+/// Neither side can possibly checkmate: only kings and a single minor piece, or only kings and bishops that all
+/// stand on squares of the same colour.
+pub fn insufficient_material(board: &Board) -> bool {
+    if [PieceType::P, PieceType::R, PieceType::Q]
+        .into_iter()
+        .any(|piece| !board.piece_bb_both_players(piece).is_empty())
+    {
+        return false;
+    }
+
+    let knights = board.piece_bb_both_players(PieceType::N);
+    let bishops = board.piece_bb_both_players(PieceType::B);
+
+    (knights | bishops).count_bits() <= 1
+        || (knights.is_empty()
+            && ((bishops & BitBoard::DARK_SQUARES).is_empty()
+                || (bishops & BitBoard::LIGHT_SQUARES).is_empty()))
+}
+
+/// Whether `colour` could checkmate by any series of legal moves, however unlikely.
+/// Running out of time against a side without mating material is a draw rather than a loss.
+///
+/// A lone king never can. A single knight or bishop can only with the help of the other side's pieces boxing their
+/// own king in, which a bishop's opponent can't do with bishops that stand on the same colour of squares as it.
+pub fn has_mating_material(board: &Board, colour: Player) -> bool {
+    if [PieceType::P, PieceType::R, PieceType::Q]
+        .into_iter()
+        .any(|piece| board.count_piece(colour, piece) > 0)
+    {
+        return true;
+    }
+
+    let knights = board.piece_bb(colour, PieceType::N);
+    let bishops = board.piece_bb(colour, PieceType::B);
+
+    match (knights | bishops).count_bits() {
+        0 => false,
+        1 => {
+            let opponent = get_opposite_colour(colour);
+            let blockers =
+                board.get_occupied_player(opponent) & !board.piece_bb(opponent, PieceType::K);
+
+            if bishops.is_empty() {
+                !blockers.is_empty()
+            } else {
+                let same_squares = if (bishops & BitBoard::DARK_SQUARES).is_empty() {
+                    BitBoard::LIGHT_SQUARES
+                } else {
+                    BitBoard::DARK_SQUARES
+                };
+
+                !(blockers & !(board.piece_bb(opponent, PieceType::B) & same_squares)).is_empty()
+            }
+        }
+        _ => true,
+    }
+}
+
+pub fn get_opposite_colour(colour: Player) -> Player {
+    if colour == Player::White {
+        Player::Black
+    } else {
+        Player::White
+    }
+}
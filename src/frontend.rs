@@ -0,0 +1,856 @@
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use pleco::{PieceType, Player};
+use poise::serenity_prelude::{
+    self as serenity, AttachmentType, ButtonStyle, Channel, GuildChannel, Http, HttpError, Message,
+    StatusCode,
+};
+use tokio::time::Instant;
+
+use crate::{
+    board_style::{BoardContent, BoardLook},
+    bot::BotLevel,
+    chess_match::THREAD_DELETION_DELAY,
+    clock::ClockMode,
+    commands::{
+        promotion::{promotion_button_id, PROMOTION_PIECES},
+        takeback::takeback_button_id,
+    },
+    database::MatchRecord,
+    engine::{
+        get_opposite_colour, ChessPlayer, GameEndReason, GameResult, GameState, MatchEngine,
+        MatchEvent, Seat,
+    },
+    notation::{to_san, IllegalMoveReason},
+    pgn::{write_pgn, PgnHeader},
+    render_queue::{board_attachment, PinnedContent, RenderQueue},
+    Error,
+};
+
+/// Displays a match to its players.
+#[async_trait]
+pub trait MatchFrontend: Send {
+    /// Shows a single event. `engine` is the state of the match after the event happened.
+    async fn render(&mut self, engine: &MatchEngine, event: &MatchEvent) -> Result<(), Error>;
+
+    /// Shows a match that was picked back up after a restart.
+    async fn resume(&mut self, engine: &MatchEngine) -> Result<(), Error>;
+
+    /// Tears the frontend down once the match is over.
+    async fn close(&mut self) -> Result<(), Error>;
+}
+
+/// Plays a match in a public Discord thread, with the board, clocks and legal moves in pinned messages.
+///
+/// The pinned messages are only edited when a move is made or the match changes state. The running clock counts
+/// down on its own, as a Discord relative timestamp.
+#[derive(Debug)]
+pub struct DiscordFrontend {
+    http: Arc<Http>,
+    match_thread_message: Message,
+    match_thread: GuildChannel,
+    board_message: Message,
+    time_info_message: Message,
+    legal_move_message: Message,
+    board_look: BoardLook,
+    render_queue: RenderQueue,
+}
+
+impl DiscordFrontend {
+    /// Posts and pins the board, clock and legal move messages in a new match thread.
+    pub async fn create(
+        http: Arc<Http>,
+        match_thread_message: Message,
+        match_thread: GuildChannel,
+        engine: &MatchEngine,
+        board_look: BoardLook,
+    ) -> Result<Self, Error> {
+        let content = pinned_content(engine, &board_look)?;
+
+        let board_message = match_thread
+            .send_message(&http, |m| match &content.board {
+                BoardContent::Image(png) => m.add_file(board_attachment(png)),
+                BoardContent::Text(text) => m.content(text),
+            })
+            .await?;
+        let time_info_message = match_thread.say(&http, &content.clocks).await?;
+        let legal_move_message = match_thread.say(&http, &content.legal_moves).await?;
+
+        legal_move_message.pin(&http).await?;
+        time_info_message.pin(&http).await?;
+        board_message.pin(&http).await?;
+
+        Ok(Self::new(
+            http,
+            match_thread_message,
+            match_thread,
+            [board_message, time_info_message, legal_move_message],
+            board_look,
+            content,
+        ))
+    }
+
+    /// Fetches the thread and pinned messages of a stored match again.
+    pub async fn reconnect(
+        http: Arc<Http>,
+        record: &MatchRecord,
+        board_look: BoardLook,
+    ) -> Result<Self, Error> {
+        let match_thread = match http.get_channel(record.match_id.0).await? {
+            Channel::Guild(match_thread) => match_thread,
+            _ => return Err(anyhow!("The match thread is no longer a guild channel.").into()),
+        };
+
+        let match_thread_message = http
+            .get_message(record.parent_channel_id.0, record.match_thread_message_id.0)
+            .await?;
+        let board_message = http
+            .get_message(record.match_id.0, record.board_message_id.0)
+            .await?;
+        let time_info_message = http
+            .get_message(record.match_id.0, record.time_info_message_id.0)
+            .await?;
+        let legal_move_message = http
+            .get_message(record.match_id.0, record.legal_move_message_id.0)
+            .await?;
+
+        // A board image that is up can't be compared, so it is always uploaded again.
+        let board = match board_look {
+            BoardLook::Image(_) => BoardContent::Image(Vec::new()),
+            BoardLook::Text(_) => BoardContent::Text(board_message.content.clone()),
+        };
+        let shown = PinnedContent {
+            board,
+            clocks: time_info_message.content.clone(),
+            legal_moves: legal_move_message.content.clone(),
+        };
+
+        Ok(Self::new(
+            http,
+            match_thread_message,
+            match_thread,
+            [board_message, time_info_message, legal_move_message],
+            board_look,
+            shown,
+        ))
+    }
+
+    fn new(
+        http: Arc<Http>,
+        match_thread_message: Message,
+        match_thread: GuildChannel,
+        [board_message, time_info_message, legal_move_message]: [Message; 3],
+        board_look: BoardLook,
+        shown: PinnedContent,
+    ) -> Self {
+        let render_queue = RenderQueue::new(
+            http.clone(),
+            board_message.clone(),
+            time_info_message.clone(),
+            legal_move_message.clone(),
+            shown,
+        );
+
+        Self {
+            http,
+            match_thread_message,
+            match_thread,
+            board_message,
+            time_info_message,
+            legal_move_message,
+            board_look,
+            render_queue,
+        }
+    }
+
+    pub fn match_thread(&self) -> &GuildChannel {
+        &self.match_thread
+    }
+
+    pub fn match_thread_message(&self) -> &Message {
+        &self.match_thread_message
+    }
+
+    pub fn board_message(&self) -> &Message {
+        &self.board_message
+    }
+
+    pub fn time_info_message(&self) -> &Message {
+        &self.time_info_message
+    }
+
+    pub fn legal_move_message(&self) -> &Message {
+        &self.legal_move_message
+    }
+
+    /// Offers the player a button for each piece their pawn can promote to. Clicks are handled by
+    /// `commands::promotion::handle_promotion_button`, as the match can't wait on them.
+    async fn ask_for_promotion(
+        &self,
+        engine: &MatchEngine,
+        player: ChessPlayer,
+        chess_move: &str,
+    ) -> Result<(), Error> {
+        let user_id = player
+            .user_id()
+            .ok_or_else(|| anyhow!("Only joined players can promote."))?;
+        let ply = engine.moves().len();
+
+        self.match_thread
+            .send_message(&self.http, |m| {
+                m.content(format!(
+                    "{}, which piece should your pawn promote to? Your clock is still running.",
+                    mention(player)
+                ))
+                .components(|c| {
+                    c.create_action_row(|row| {
+                        for piece in PROMOTION_PIECES {
+                            row.create_button(|button| {
+                                button
+                                    .custom_id(promotion_button_id(user_id, ply, chess_move, piece))
+                                    .label(piece)
+                                    .style(ButtonStyle::Primary)
+                            });
+                        }
+                        row
+                    })
+                })
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Asks the opponent of `player` to accept or decline their takeback with a pair of buttons. Clicks are
+    /// handled by `commands::takeback::handle_takeback_button`.
+    async fn ask_for_takeback(
+        &self,
+        engine: &MatchEngine,
+        player: ChessPlayer,
+        moves: usize,
+    ) -> Result<(), Error> {
+        let opponent = engine.player_with_colour(get_opposite_colour(player.colour()));
+        let opponent_id = opponent
+            .user_id()
+            .ok_or_else(|| anyhow!("Only joined players can answer takebacks."))?;
+        let ply = engine.moves().len();
+
+        self.match_thread
+            .send_message(&self.http, |m| {
+                m.content(format!(
+                    "{}, {} ({}) asks to take back {}.",
+                    mention(opponent),
+                    mention(player),
+                    player.colour(),
+                    describe_takeback(moves)
+                ))
+                .components(|c| {
+                    c.create_action_row(|row| {
+                        row.create_button(|button| {
+                            button
+                                .custom_id(takeback_button_id(opponent_id, ply, true))
+                                .label("Accept")
+                                .style(ButtonStyle::Success)
+                        })
+                        .create_button(|button| {
+                            button
+                                .custom_id(takeback_button_id(opponent_id, ply, false))
+                                .label("Decline")
+                                .style(ButtonStyle::Danger)
+                        })
+                    })
+                })
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Posts the game as a PGN file in the parent channel, where it stays once the thread is deleted.
+    async fn post_pgn(&self, engine: &MatchEngine) -> Result<(), Error> {
+        if engine.moves().is_empty() {
+            return Ok(());
+        }
+
+        let header = PgnHeader {
+            white: self
+                .player_name(engine.player_with_colour(Player::White))
+                .await,
+            black: self
+                .player_name(engine.player_with_colour(Player::Black))
+                .await,
+            date: SystemTime::now(),
+        };
+        let pgn = write_pgn(engine, &header);
+
+        self.match_thread_message
+            .channel_id
+            .send_message(&self.http, |m| {
+                m.content(format!("{} vs {}", header.white, header.black))
+                    .add_file(AttachmentType::Bytes {
+                        data: Cow::Owned(pgn.into_bytes()),
+                        filename: format!("threadrook-{}.pgn", self.match_thread.id),
+                    })
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn player_name(&self, player: ChessPlayer) -> String {
+        match player.seat {
+            Seat::Member(user_id) => match user_id.to_user(&self.http).await {
+                Ok(user) => user.name,
+                Err(_) => "?".to_string(),
+            },
+            Seat::Bot(level) => bot_name(level),
+            Seat::Open => "?".to_string(),
+        }
+    }
+
+    async fn say(&self, content: impl std::fmt::Display) -> Result<(), Error> {
+        self.match_thread.say(&self.http, content).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MatchFrontend for DiscordFrontend {
+    async fn render(&mut self, engine: &MatchEngine, event: &MatchEvent) -> Result<(), Error> {
+        match event {
+            MatchEvent::OpponentJoined(user_id) => {
+                self.say(format!("<@{}> just joined", user_id)).await?;
+            }
+            MatchEvent::JoinRejected(user_id) => {
+                self.say(format!(
+                    "<@{}> tried to join, but this match already has two players.",
+                    user_id
+                ))
+                .await?;
+            }
+            MatchEvent::GameStarted => {
+                self.say("The match has now started!").await?;
+            }
+            MatchEvent::MoveApplied { player, san, .. } => {
+                self.say(format!(
+                    "{} ({}) played {}",
+                    mention(*player),
+                    player.colour(),
+                    san
+                ))
+                .await?;
+            }
+            MatchEvent::IllegalMove { chess_move, reason } => {
+                self.say(format!(
+                    "{} is not a legal move. {}",
+                    chess_move,
+                    describe_illegal_move(engine, *reason)
+                ))
+                .await?;
+            }
+            MatchEvent::PromotionNeeded { player, chess_move } => {
+                self.ask_for_promotion(engine, *player, chess_move).await?;
+            }
+            MatchEvent::UnreadableMove(chess_move) => {
+                self.say(format!(
+                    "{} is not a move. Write moves like `e4`, `Nf3`, `exd5`, `O-O` or `e8=Q`. Use `/move_notation_guide` for help.",
+                    chess_move
+                ))
+                .await?;
+            }
+            MatchEvent::AmbiguousMove {
+                chess_move,
+                candidates,
+            } => {
+                self.say(format!(
+                    "{} could be any of {}. Add the file or rank the piece moves from, e.g. `Nbd7`, or the promotion piece, e.g. `e8=Q`.",
+                    chess_move,
+                    candidates.join(", ")
+                ))
+                .await?;
+            }
+            MatchEvent::NotAPlayer(_) => {
+                self.say("You are not a player in this match.").await?;
+            }
+            MatchEvent::DrawOffered(player) => {
+                self.say(format!(
+                    "{} ({}) offers a draw. Use `/accept_draw` or `/decline_draw` to answer.",
+                    mention(*player),
+                    player.colour()
+                ))
+                .await?;
+            }
+            MatchEvent::DrawDeclined(player) => {
+                self.say(format!(
+                    "{} ({}) declined the draw offer.",
+                    mention(*player),
+                    player.colour()
+                ))
+                .await?;
+            }
+            MatchEvent::DrawOfferLapsed(player) => {
+                self.say(format!(
+                    "{}'s draw offer lapsed, as they moved again.",
+                    mention(*player)
+                ))
+                .await?;
+            }
+            MatchEvent::DrawClaimRejected(user_id) => {
+                self.say(format!(
+                    "<@{}>, there is no draw to claim. The position hasn't occurred three times, and there has been a capture or pawn move in the last 50 moves.",
+                    user_id
+                ))
+                .await?;
+            }
+            MatchEvent::TakebackRequested { player, moves } => {
+                self.ask_for_takeback(engine, *player, *moves).await?;
+            }
+            MatchEvent::TakebackAccepted { player, moves } => {
+                self.say(format!(
+                    "{} took back {}. {} to move.",
+                    mention(*player),
+                    describe_takeback(*moves),
+                    engine.acting_colour()
+                ))
+                .await?;
+            }
+            MatchEvent::TakebackDeclined(player) => {
+                self.say(format!(
+                    "{} ({}) declined the takeback.",
+                    mention(*player),
+                    player.colour()
+                ))
+                .await?;
+            }
+            MatchEvent::NothingToTakeBack(user_id) => {
+                self.say(format!(
+                    "<@{}>, you haven't made a move that can be taken back.",
+                    user_id
+                ))
+                .await?;
+            }
+            MatchEvent::TakebacksNotAllowed(user_id) => {
+                self.say(format!(
+                    "<@{}>, takebacks aren't allowed in rated matches.",
+                    user_id
+                ))
+                .await?;
+            }
+            MatchEvent::NoTakebackRequest(user_id) => {
+                self.say(format!(
+                    "<@{}>, your opponent hasn't asked for a takeback, or has moved since.",
+                    user_id
+                ))
+                .await?;
+            }
+            MatchEvent::PremoveDropped {
+                user_id,
+                chess_move,
+            } => {
+                // Told privately, so the opponent doesn't learn what was planned.
+                let notice = format!(
+                    "Your premove {} in {} couldn't be played after your opponent's move, so it was dropped.",
+                    chess_move, self.match_thread
+                );
+                let sent = match user_id.create_dm_channel(&self.http).await {
+                    Ok(dm_channel) => dm_channel.say(&self.http, notice).await.map(|_| ()),
+                    Err(error) => Err(error),
+                };
+                if let Err(error) = sent {
+                    tracing::warn!(
+                        "Unable to tell {} their premove was dropped: {}",
+                        user_id,
+                        error
+                    );
+                }
+            }
+            MatchEvent::NoDrawOffer(user_id) => {
+                self.say(format!(
+                    "<@{}>, your opponent hasn't offered a draw.",
+                    user_id
+                ))
+                .await?;
+            }
+            MatchEvent::GameEnded { result, reason } => {
+                self.say(describe_game_end(engine, *result, *reason))
+                    .await?;
+                // The game is over either way, so a PGN that couldn't be posted mustn't stop the thread being closed.
+                if let Err(error) = self.post_pgn(engine).await {
+                    tracing::warn!(
+                        "Unable to post the PGN of match {}: {}",
+                        self.match_thread.id,
+                        error
+                    );
+                }
+                self.say(format!(
+                    "The match is over. Deleting thread in {} secs...",
+                    THREAD_DELETION_DELAY.as_secs()
+                ))
+                .await?;
+            }
+        }
+
+        self.render_queue
+            .show(pinned_content(engine, &self.board_look)?);
+
+        Ok(())
+    }
+
+    async fn resume(&mut self, engine: &MatchEngine) -> Result<(), Error> {
+        self.say("ThreadRook restarted. Picking the match back up...")
+            .await?;
+        self.render_queue
+            .show(pinned_content(engine, &self.board_look)?);
+
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<(), Error> {
+        self.render_queue.close();
+        self.match_thread.delete(&self.http).await?;
+        self.match_thread_message.delete(&self.http).await?;
+
+        Ok(())
+    }
+}
+
+/// Keeps every rendered event in memory, which is handy for driving matches without Discord.
+/// Clones share the same recording.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingFrontend {
+    recording: Arc<Mutex<Recording>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    pub events: Vec<MatchEvent>,
+    pub resumed: bool,
+    pub closed: bool,
+}
+
+impl RecordingFrontend {
+    pub fn recording(&self) -> Recording {
+        self.recording
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn update(&self, update: impl FnOnce(&mut Recording)) {
+        update(
+            &mut self
+                .recording
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+    }
+}
+
+#[async_trait]
+impl MatchFrontend for RecordingFrontend {
+    async fn render(&mut self, _engine: &MatchEngine, event: &MatchEvent) -> Result<(), Error> {
+        self.update(|recording| recording.events.push(event.clone()));
+
+        Ok(())
+    }
+
+    async fn resume(&mut self, _engine: &MatchEngine) -> Result<(), Error> {
+        self.update(|recording| recording.resumed = true);
+
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<(), Error> {
+        self.update(|recording| recording.closed = true);
+
+        Ok(())
+    }
+}
+
+fn mention(player: ChessPlayer) -> String {
+    match player.seat {
+        Seat::Member(user_id) => format!("<@{}>", user_id),
+        Seat::Bot(level) => bot_name(level),
+        Seat::Open => "Nobody".to_string(),
+    }
+}
+
+/// Whether Discord answered that a thread or message no longer exists, as opposed to failing to answer at all.
+pub fn is_gone_from_discord(error: &Error) -> bool {
+    // Discord's codes for Unknown Channel and Unknown Message.
+    const GONE: [isize; 2] = [10003, 10008];
+
+    match error.downcast_ref::<serenity::Error>() {
+        Some(serenity::Error::Http(http_error)) => match &**http_error {
+            HttpError::UnsuccessfulRequest(response) => {
+                response.status_code == StatusCode::NOT_FOUND || GONE.contains(&response.error.code)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+pub fn bot_name(level: BotLevel) -> String {
+    format!("ThreadRook Bot ({})", level)
+}
+
+/// The pinned board is drawn from the point of view of the player to move.
+fn pinned_content(engine: &MatchEngine, board_look: &BoardLook) -> Result<PinnedContent, Error> {
+    Ok(PinnedContent {
+        board: board_look.draw(engine.board(), engine.acting_colour())?,
+        clocks: describe_clocks(engine),
+        legal_moves: describe_legal_moves(engine),
+    })
+}
+
+fn describe_takeback(moves: usize) -> &'static str {
+    if moves == 1 {
+        "their last move"
+    } else {
+        "their last move and the reply to it"
+    }
+}
+
+/// The clock message. Nothing in it changes until the next move, as the running clock is shown as the
+/// relative timestamp its flag falls at.
+fn describe_clocks(engine: &MatchEngine) -> String {
+    if engine.state() == GameState::WaitingForOpponent {
+        return format!(
+            "{}\nDeadline for an opponent to join: {}",
+            describe_time_control(engine),
+            relative_timestamp(engine.join_deadline())
+        );
+    }
+
+    let now = Instant::now();
+    let flag_deadline = engine
+        .deadline()
+        .filter(|_| engine.state() == GameState::Playing);
+    let describe_clock = |player: ChessPlayer| {
+        let colour = player.colour();
+        match flag_deadline {
+            Some(flag_deadline) if colour == engine.acting_colour() => format!(
+                "{} ({}) Time: {}, runs out {}",
+                mention(player),
+                colour,
+                format_clock(engine.clock().remaining_at_turn_start(colour)),
+                relative_timestamp(flag_deadline)
+            ),
+            _ => format!(
+                "{} ({}) Time: {}",
+                mention(player),
+                colour,
+                format_clock(engine.get_colour_timeleft(colour, now))
+            ),
+        }
+    };
+
+    format!(
+        "{}\n{}\n{}",
+        describe_time_control(engine),
+        describe_clock(engine.player_one()),
+        describe_clock(engine.player_two())
+    )
+}
+
+fn describe_legal_moves(engine: &MatchEngine) -> String {
+    if engine.state() == GameState::WaitingForOpponent {
+        return format!(
+            "{}'s legal moves in the current position: _",
+            engine.acting_colour()
+        );
+    }
+
+    let board = engine.board();
+    let legal_moves = board.generate_moves();
+    let groups: Vec<String> = [
+        (PieceType::K, "King"),
+        (PieceType::Q, "Queens"),
+        (PieceType::R, "Rooks"),
+        (PieceType::B, "Bishops"),
+        (PieceType::N, "Knights"),
+        (PieceType::P, "Pawns"),
+    ]
+    .into_iter()
+    .filter_map(|(piece, name)| {
+        let mut moves: Vec<String> = legal_moves
+            .iter()
+            .filter(|legal_move| board.piece_at_sq(legal_move.get_src()).type_of() == piece)
+            .map(|legal_move| to_san(board, *legal_move))
+            .collect();
+        moves.sort();
+
+        (!moves.is_empty()).then(|| format!("{}: {}", name, moves.join(", ")))
+    })
+    .collect();
+
+    if groups.is_empty() {
+        return format!("{} has no legal moves.", engine.acting_colour());
+    }
+
+    format!(
+        "{}'s legal moves in the current position:\n{}",
+        engine.acting_colour(),
+        groups.join(" · ")
+    )
+}
+
+fn describe_illegal_move(engine: &MatchEngine, reason: IllegalMoveReason) -> String {
+    let piece_name = |piece: PieceType| piece.to_string().to_lowercase();
+
+    match reason {
+        IllegalMoveReason::NotYourTurn => {
+            format!("It is {}'s turn.", engine.acting_colour())
+        }
+        IllegalMoveReason::NoPiece(square) => format!("There is no piece on {}.", square),
+        IllegalMoveReason::OpponentsPiece(square) => {
+            format!("The piece on {} belongs to your opponent.", square)
+        }
+        IllegalMoveReason::CantReach {
+            piece,
+            from: Some(from),
+            to,
+        } => format!(
+            "The {} on {} can't move to {}.",
+            piece_name(piece),
+            from,
+            to
+        ),
+        IllegalMoveReason::CantReach {
+            piece,
+            from: None,
+            to,
+        } => format!("None of your {}s can move to {}.", piece_name(piece), to),
+        IllegalMoveReason::Pinned { piece, from } => format!(
+            "The {} on {} is pinned: moving it would put your king in check.",
+            piece_name(piece),
+            from
+        ),
+        IllegalMoveReason::InCheck => {
+            "Your king is in check, and that move doesn't get it out of check.".to_string()
+        }
+        IllegalMoveReason::KingIntoCheck => {
+            "Your king can't move onto a square your opponent attacks.".to_string()
+        }
+        IllegalMoveReason::CastlingRightsLost => {
+            "You can no longer castle on that side, as your king or that rook has already moved."
+                .to_string()
+        }
+        IllegalMoveReason::CastlingBlocked => {
+            "You can't castle while there are pieces between your king and rook.".to_string()
+        }
+        IllegalMoveReason::CastlingThroughCheck => {
+            "You can't castle out of, through or into check.".to_string()
+        }
+    }
+}
+
+/// A Discord timestamp that counts down to `deadline` on every client, e.g. "in 4 minutes".
+fn relative_timestamp(deadline: Instant) -> String {
+    let now = Instant::now();
+    let wall_clock = if deadline >= now {
+        SystemTime::now() + (deadline - now)
+    } else {
+        SystemTime::now() - (now - deadline)
+    };
+    let unix_time = wall_clock
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    format!("<t:{}:R>", unix_time)
+}
+
+/// Minutes and seconds, with tenths once the clock is down to its last ten seconds.
+pub fn format_clock(time_left: Duration) -> String {
+    // Rounded before choosing a format, so 9.96 seconds isn't shown as "0:010.0".
+    let tenths = (time_left.as_secs_f64() * 10.0).round() as u64;
+
+    if tenths < 100 {
+        format!("0:0{}.{}", tenths / 10, tenths % 10)
+    } else {
+        let secs = tenths / 10;
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+fn describe_time_control(engine: &MatchEngine) -> String {
+    let time_control = engine.time_control();
+
+    match time_control.mode {
+        ClockMode::Fischer => format!(
+            "Time control: {} ({})",
+            time_control,
+            time_control.category()
+        ),
+        mode => format!(
+            "Time control: {} ({}, {})",
+            time_control,
+            time_control.category(),
+            mode
+        ),
+    }
+}
+
+fn describe_game_end(engine: &MatchEngine, result: GameResult, reason: GameEndReason) -> String {
+    let winner = match result {
+        GameResult::Winner(colour) => Some(engine.player_with_colour(colour)),
+        _ => None,
+    };
+
+    match (reason, winner) {
+        (GameEndReason::Checkmate, Some(winner)) => format!(
+            "{} ({}) delivered checkmate. GG.",
+            mention(winner),
+            winner.colour()
+        ),
+        (GameEndReason::Resignation(colour), _) => format!(
+            "{} ({}) resigned. {} wins. GG.",
+            mention(engine.player_with_colour(colour)),
+            colour,
+            get_opposite_colour(colour)
+        ),
+        (GameEndReason::Timeout(colour), _) => format!(
+            "{} just lost on time. {} wins. GG.",
+            colour,
+            get_opposite_colour(colour)
+        ),
+        (GameEndReason::TimeoutVsInsufficientMaterial(colour), _) => format!(
+            "{} ran out of time, but {} doesn't have enough material left to checkmate. The game is a draw. GG.",
+            colour,
+            get_opposite_colour(colour)
+        ),
+        (GameEndReason::Stalemate, _) => "Stalemate. The game is a draw. GG.".to_string(),
+        (GameEndReason::Agreement, _) => "Both players agreed to a draw. GG.".to_string(),
+        (GameEndReason::InsufficientMaterial, _) => {
+            "Neither side has enough material left to checkmate. The game is a draw. GG."
+                .to_string()
+        }
+        (GameEndReason::FivefoldRepetition, _) => {
+            "The same position occurred five times. The game is a draw. GG.".to_string()
+        }
+        (GameEndReason::SeventyFiveMoveRule, _) => {
+            "75 moves went by without a capture or pawn move. The game is a draw. GG.".to_string()
+        }
+        (GameEndReason::ThreefoldRepetition, _) => {
+            "A draw was claimed, as the same position occurred three times. GG.".to_string()
+        }
+        (GameEndReason::FiftyMoveRule, _) => {
+            "A draw was claimed, as 50 moves went by without a capture or pawn move. GG."
+                .to_string()
+        }
+        (GameEndReason::Cancelled, _) => {
+            format!("{} cancelled the match.", mention(engine.player_one()))
+        }
+        (GameEndReason::NoOpponent, _) => "Nobody joined the match in time.".to_string(),
+        (GameEndReason::Checkmate, None) => "Checkmate. GG.".to_string(),
+    }
+}
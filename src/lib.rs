@@ -1,29 +1,58 @@
+pub mod board_image;
+pub mod board_style;
+pub mod board_text;
+pub mod bot;
 pub mod chess_match;
+pub mod clock;
 pub mod commands;
+pub mod database;
+pub mod engine;
+pub mod frontend;
+pub mod match_registry;
+pub mod notation;
+pub mod pgn;
+pub mod render_queue;
 
-use poise::serenity_prelude::{GuildId, Member};
-use tokio::sync::{broadcast, mpsc};
+use std::sync::Arc;
+
+use database::Database;
+use engine::BoardView;
+use match_registry::MatchRegistry;
+use notation::LegalMove;
+use poise::serenity_prelude::UserId;
+use tokio::sync::oneshot;
 extern crate pleco;
 
 // User data, which is stored and accessible in all command invocations
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Data {
-    pub system_communication_channel: (
-        broadcast::Sender<DiscordCommunication>,
-        broadcast::Receiver<DiscordCommunication>,
-    ),
+    pub match_registry: Arc<MatchRegistry>,
+    pub database: Arc<Database>,
 }
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 pub type Context<'a> = poise::Context<'a, Data, Error>;
 
-#[derive(Debug, Clone)]
-pub struct DiscordCommunication(GuildId, DiscordCommand);
-
-#[derive(Debug, Clone)]
-enum DiscordCommand {
-    JoinMatch(Box<Member>, Box<Member>),
-    MakeMove(Box<Member>, String),
-    Resign(Box<Member>),
-    VerifyIfAlreadyInMatch(Box<Member>, mpsc::Sender<bool>),
-    TimeTick,
+#[derive(Debug)]
+pub enum DiscordCommand {
+    JoinMatch(UserId),
+    MakeMove(UserId, String),
+    Resign(UserId),
+    OfferDraw(UserId),
+    AcceptDraw(UserId),
+    DeclineDraw(UserId),
+    ClaimDraw(UserId),
+    RequestTakeback(UserId),
+    /// Answers a takeback request made after the given number of moves, replying whether the answer was taken.
+    /// Answers to a request from before a move was played or taken back are ignored.
+    AcceptTakeback(UserId, usize, oneshot::Sender<bool>),
+    DeclineTakeback(UserId, usize, oneshot::Sender<bool>),
+    /// Plays a promotion picked with a button offered after the given number of moves, replying whether it still
+    /// fits the position. Buttons from before a move was played or taken back are ignored.
+    PickPromotion(UserId, usize, String, oneshot::Sender<bool>),
+    /// Asks for the moves the user could make right now, e.g. to autocomplete `/make_move`.
+    ListLegalMoves(UserId, oneshot::Sender<Vec<LegalMove>>),
+    /// Throws away the user's premove, replying with the move if there was one.
+    CancelPremove(UserId, oneshot::Sender<Option<String>>),
+    /// Asks for a copy of the board, e.g. to draw it for the user.
+    ViewBoard(UserId, oneshot::Sender<BoardView>),
 }
@@ -1,35 +1,65 @@
+use std::sync::Arc;
+
 use anyhow::Context;
 use poise::serenity_prelude as serenity;
 use shuttle_poise::ShuttlePoise;
 use shuttle_secrets::SecretStore;
-use tokio::sync::broadcast;
 
 use threadrook::{
-    commands::{create_match::*, join_match::*, make_move::*, move_notation_guide::*, resign::*},
+    chess_match::{ChessMatch, MatchContext},
+    commands::{
+        accept_draw::*, board::*, board_style::*, cancel_premove::*, claim_draw::*,
+        create_match::*, decline_draw::*, join_match::*, make_move::*, move_notation_guide::*,
+        offer_draw::*, play_bot::*, promotion::handle_promotion_button, resign::*, takeback::*,
+    },
+    database::Database,
     Data, Error,
 };
 
 #[shuttle_runtime::main]
 async fn poise(#[shuttle_secrets::Secrets] secret_store: SecretStore) -> ShuttlePoise<Data, Error> {
-    // broadcast channel that allows both external Discord command invocations and internal, spawned tasks to communicate with chess matches
-    // Chess matches actually only use one broadcast receiver at a time.
-    // The reason why an mpsc wasn't used was because there is no way of utilizing a receiver without it being mutable. Data references are immutable.
-    let (tx, rx) = broadcast::channel(10000);
-
     // Get the discord token set in `Secrets.toml`.
     let discord_token = secret_store
         .get("DISCORD_TOKEN")
         .context("'DISCORD_TOKEN' was not found")?;
 
+    // Matches are stored in SQLite so they can be picked back up after a restart.
+    let database_path = secret_store
+        .get("DATABASE_PATH")
+        .unwrap_or_else(|| "threadrook.sqlite3".to_string());
+    let database = Database::open(database_path).map_err(shuttle_runtime::CustomError::msg)?;
+
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
             commands: vec![
                 create_match(),
                 join_match(),
+                play_bot(),
                 make_move(),
+                cancel_premove(),
                 move_notation_guide(),
                 resign(),
+                offer_draw(),
+                accept_draw(),
+                decline_draw(),
+                claim_draw(),
+                takeback(),
+                board(),
+                board_style(),
             ],
+            event_handler: |ctx, event, _framework, data| {
+                Box::pin(async move {
+                    if let poise::Event::InteractionCreate {
+                        interaction: serenity::Interaction::MessageComponent(component),
+                    } = event
+                    {
+                        handle_promotion_button(ctx, component, data).await?;
+                        handle_takeback_button(ctx, component, data).await?;
+                    }
+
+                    Ok(())
+                })
+            },
             ..Default::default()
         })
         .token(discord_token)
@@ -37,9 +67,14 @@ async fn poise(#[shuttle_secrets::Secrets] secret_store: SecretStore) -> Shuttle
         .setup(|ctx, _ready, framework| {
             Box::pin(async move {
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
-                Ok(Data {
-                    system_communication_channel: (tx, rx),
-                })
+                let data = Data {
+                    match_registry: Default::default(),
+                    database: Arc::new(database),
+                };
+
+                ChessMatch::restore_all(MatchContext::new(ctx.http.clone(), data.clone()))?;
+
+                Ok(data)
             })
         })
         .build()
@@ -0,0 +1,346 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use anyhow::anyhow;
+use poise::serenity_prelude::{ChannelId, GuildId, UserId};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+
+use crate::{engine::BoardView, notation::LegalMove, DiscordCommand, Error};
+
+/// Matches are identified by the id of the thread they are played in.
+pub type MatchId = ChannelId;
+
+const MATCH_CHANNEL_CAPACITY: usize = 64;
+
+/// A command on its way to a match, stamped with when it was sent.
+/// Moves are timed from `sent_at`, so players aren't charged for the time a command spends queued.
+#[derive(Debug)]
+pub struct MatchInput {
+    pub command: DiscordCommand,
+    pub sent_at: Instant,
+}
+
+impl MatchInput {
+    pub fn new(command: DiscordCommand) -> Self {
+        Self {
+            command,
+            sent_at: Instant::now(),
+        }
+    }
+}
+
+/// Routes commands to the chess match they are meant for.
+///
+/// Every running match owns the receiving half of its own mpsc channel. The registry keeps the sending halves,
+/// along with an index of which match each member is playing in.
+#[derive(Debug, Default)]
+pub struct MatchRegistry {
+    inner: Mutex<RegistryInner>,
+}
+
+#[derive(Debug, Default)]
+struct RegistryInner {
+    matches: HashMap<MatchId, RegisteredMatch>,
+    members: HashMap<(GuildId, UserId), MatchId>,
+    /// Members whose match is still being set up.
+    reserved: HashSet<(GuildId, UserId)>,
+}
+
+#[derive(Debug)]
+struct RegisteredMatch {
+    match_tx: mpsc::Sender<MatchInput>,
+    /// How many members can play in the match: two, or one in a game against a bot.
+    member_seats: usize,
+}
+
+/// A member's place in a match that is still being set up, which keeps them out of every other match until the
+/// new one is registered. The place is given up when this is dropped.
+#[derive(Debug)]
+pub struct Reservation {
+    registry: Arc<MatchRegistry>,
+    guild_id: GuildId,
+    user_id: UserId,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.registry
+            .lock()
+            .reserved
+            .remove(&(self.guild_id, self.user_id));
+    }
+}
+
+impl MatchRegistry {
+    /// Claims a place for a member who is setting up a new match, unless they are already in one.
+    pub fn reserve(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> Result<Reservation, Error> {
+        let mut inner = self.lock();
+        if inner.is_busy(guild_id, user_id) {
+            return Err(anyhow!("You are already in a match. Threadrook currently only supports users competing in a single match at a time per server.").into());
+        }
+        inner.reserved.insert((guild_id, user_id));
+
+        Ok(Reservation {
+            registry: self.clone(),
+            guild_id,
+            user_id,
+        })
+    }
+
+    /// Opens a channel for a match and records its players as its members. Once `member_seats` members are
+    /// playing, nobody else can join.
+    pub(crate) fn register(
+        &self,
+        match_id: MatchId,
+        guild_id: GuildId,
+        player_ids: &[UserId],
+        member_seats: usize,
+    ) -> Result<mpsc::Receiver<MatchInput>, Error> {
+        let mut inner = self.lock();
+        if player_ids
+            .iter()
+            .any(|player_id| inner.members.contains_key(&(guild_id, *player_id)))
+        {
+            return Err(anyhow!("You are already in a match.").into());
+        }
+
+        let (tx, rx) = mpsc::channel(MATCH_CHANNEL_CAPACITY);
+        inner.matches.insert(
+            match_id,
+            RegisteredMatch {
+                match_tx: tx,
+                member_seats,
+            },
+        );
+        for player_id in player_ids {
+            inner.members.insert((guild_id, *player_id), match_id);
+        }
+
+        Ok(rx)
+    }
+
+    /// The match a member is currently playing in, if any.
+    pub fn match_of(&self, guild_id: GuildId, user_id: UserId) -> Option<MatchId> {
+        self.lock().members.get(&(guild_id, user_id)).copied()
+    }
+
+    /// Seats a member as the opponent in another member's match and lets the match know they joined.
+    ///
+    /// The seat is claimed before this returns, so a member can never end up in two matches at once.
+    pub async fn join(
+        &self,
+        guild_id: GuildId,
+        waiting_user_id: UserId,
+        joining_user_id: UserId,
+    ) -> Result<(), Error> {
+        let match_tx = {
+            let mut inner = self.lock();
+
+            if waiting_user_id == joining_user_id {
+                return Err(anyhow!("You can't join your own match.").into());
+            }
+            if inner.is_busy(guild_id, joining_user_id) {
+                return Err(anyhow!("You are already in a match. Threadrook currently only supports users competing in a single match at a time per server.").into());
+            }
+
+            let match_id = *inner
+                .members
+                .get(&(guild_id, waiting_user_id))
+                .ok_or_else(|| anyhow!("That member is not in a match in this server."))?;
+            let registered = inner
+                .matches
+                .get(&match_id)
+                .ok_or_else(|| anyhow!("That match has already finished."))?;
+            let match_tx = registered.match_tx.clone();
+            if registered.member_seats < 2 {
+                return Err(anyhow!("That member is playing against a bot.").into());
+            }
+            if inner.members.values().filter(|id| **id == match_id).count() >= 2 {
+                return Err(anyhow!("That match already has two players.").into());
+            }
+
+            inner.members.insert((guild_id, joining_user_id), match_id);
+            match_tx
+        };
+
+        match_tx
+            .send(MatchInput::new(DiscordCommand::JoinMatch(joining_user_id)))
+            .await
+            .map_err(|_| anyhow!("That match has already finished.").into())
+    }
+
+    /// Forgets a match and every member that was playing in it. Calling this more than once is harmless.
+    pub(crate) fn deregister(&self, match_id: MatchId) {
+        let mut inner = self.lock();
+        inner.matches.remove(&match_id);
+        inner
+            .members
+            .retain(|_, member_match_id| *member_match_id != match_id);
+    }
+
+    /// Sends a command to the match that a member is playing in.
+    pub async fn send_to_member(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        command: DiscordCommand,
+    ) -> Result<(), Error> {
+        let match_tx = {
+            let inner = self.lock();
+            inner
+                .members
+                .get(&(guild_id, user_id))
+                .and_then(|match_id| inner.matches.get(match_id))
+                .map(|registered| registered.match_tx.clone())
+        };
+
+        match match_tx {
+            Some(match_tx) => match_tx
+                .send(MatchInput::new(command))
+                .await
+                .map_err(|_| anyhow!("That match has already finished.").into()),
+            None => Err(anyhow!("That member is not in a match in this server.").into()),
+        }
+    }
+
+    /// The moves a member could make right now in their match. Empty unless it is their turn.
+    pub async fn legal_moves(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> Result<Vec<LegalMove>, Error> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.send_to_member(
+            guild_id,
+            user_id,
+            DiscordCommand::ListLegalMoves(user_id, reply_tx),
+        )
+        .await?;
+
+        reply_rx
+            .await
+            .map_err(|_| anyhow!("That match has already finished.").into())
+    }
+
+    /// Accepts or declines the takeback request the member was shown after `ply` moves.
+    /// Returns `false` if the request had already been answered, withdrawn, or overtaken by another move.
+    pub async fn answer_takeback(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        ply: usize,
+        accept: bool,
+    ) -> Result<bool, Error> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let command = if accept {
+            DiscordCommand::AcceptTakeback(user_id, ply, reply_tx)
+        } else {
+            DiscordCommand::DeclineTakeback(user_id, ply, reply_tx)
+        };
+        self.send_to_member(guild_id, user_id, command).await?;
+
+        reply_rx
+            .await
+            .map_err(|_| anyhow!("That match has already finished.").into())
+    }
+
+    /// Plays the promotion a member picked with a button offered after `ply` moves.
+    /// Returns `false`, without playing it, if the match has moved on since.
+    pub async fn pick_promotion(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        ply: usize,
+        chess_move: String,
+    ) -> Result<bool, Error> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.send_to_member(
+            guild_id,
+            user_id,
+            DiscordCommand::PickPromotion(user_id, ply, chess_move, reply_tx),
+        )
+        .await?;
+
+        reply_rx
+            .await
+            .map_err(|_| anyhow!("That match has already finished.").into())
+    }
+
+    /// Throws away the member's premove, returning the move if they had one.
+    pub async fn cancel_premove(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> Result<Option<String>, Error> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.send_to_member(
+            guild_id,
+            user_id,
+            DiscordCommand::CancelPremove(user_id, reply_tx),
+        )
+        .await?;
+
+        reply_rx
+            .await
+            .map_err(|_| anyhow!("That match has already finished.").into())
+    }
+
+    /// A copy of the board of the match played in `channel_id`, or otherwise of the member's own match.
+    /// Anyone can look at a match from its thread.
+    pub async fn view_board(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        channel_id: ChannelId,
+    ) -> Result<BoardView, Error> {
+        let match_tx = {
+            let inner = self.lock();
+            inner
+                .matches
+                .get(&channel_id)
+                .or_else(|| {
+                    inner
+                        .members
+                        .get(&(guild_id, user_id))
+                        .and_then(|match_id| inner.matches.get(match_id))
+                })
+                .map(|registered| registered.match_tx.clone())
+        };
+        let match_tx = match_tx.ok_or_else(|| {
+            anyhow!("There is no match in this thread, and you are not playing in one.")
+        })?;
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        match_tx
+            .send(MatchInput::new(DiscordCommand::ViewBoard(
+                user_id, reply_tx,
+            )))
+            .await
+            .map_err(|_| anyhow!("That match has already finished."))?;
+
+        reply_rx
+            .await
+            .map_err(|_| anyhow!("That match has already finished.").into())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RegistryInner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl RegistryInner {
+    /// Whether the member is playing in a match, or setting one up.
+    fn is_busy(&self, guild_id: GuildId, user_id: UserId) -> bool {
+        self.members.contains_key(&(guild_id, user_id))
+            || self.reserved.contains(&(guild_id, user_id))
+    }
+}
@@ -0,0 +1,460 @@
+use anyhow::anyhow;
+use pleco::{core::CastleType, BitMove, Board, PieceType, Player, SQ};
+
+use crate::Error;
+
+/// Why a typed move couldn't be turned into a legal move.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MoveInputError {
+    /// The input isn't written in any notation we understand.
+    Unreadable,
+    /// No legal move fits the input.
+    Illegal,
+    /// More than one legal move fits the input.
+    Ambiguous(Vec<BitMove>),
+    /// A pawn move to the last rank that doesn't say what to promote to.
+    MissingPromotion { from: SQ, to: SQ },
+}
+
+/// Why a readable move can't be played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IllegalMoveReason {
+    NotYourTurn,
+    NoPiece(SQ),
+    OpponentsPiece(SQ),
+    /// The piece, or every piece of its kind when no source square was given, can't move to the square.
+    CantReach {
+        piece: PieceType,
+        from: Option<SQ>,
+        to: SQ,
+    },
+    /// Moving the piece would expose the king.
+    Pinned {
+        piece: PieceType,
+        from: SQ,
+    },
+    /// The king is in check and the move doesn't get it out.
+    InCheck,
+    /// The king would move onto an attacked square.
+    KingIntoCheck,
+    /// The king or the rook has already moved.
+    CastlingRightsLost,
+    /// There are pieces between the king and the rook.
+    CastlingBlocked,
+    /// The king is in check, or would pass through or land on an attacked square.
+    CastlingThroughCheck,
+}
+
+/// A legal move written both ways, for suggesting to players.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegalMove {
+    pub san: String,
+    pub uci: String,
+}
+
+/// What a move written as SAN, long algebraic or UCI says about itself.
+/// Every part the input leaves out matches any legal move.
+#[derive(Debug, Default)]
+struct MoveInput {
+    piece: Option<PieceType>,
+    from_file: Option<u8>,
+    from_rank: Option<u8>,
+    to: Option<SQ>,
+    promotion: Option<PieceType>,
+    castle: Option<Castle>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Castle {
+    KingSide,
+    QueenSide,
+}
+
+/// Finds the legal move that `input` stands for.
+///
+/// Accepts SAN (`e4`, `Nf3`, `exd5`, `Rad1`, `e8=Q`), long algebraic (`Ng1-f3`, `e7xd8Q`), UCI (`e2e4`, `e7e8q`)
+/// and castling as either `O-O` or `0-0`. Check and mate markers and annotations like `!?` are ignored.
+pub fn parse_move(board: &Board, input: &str) -> Result<BitMove, MoveInputError> {
+    let move_input = read_move(input).ok_or(MoveInputError::Unreadable)?;
+
+    let candidates: Vec<BitMove> = board
+        .generate_moves()
+        .iter()
+        .copied()
+        .filter(|legal_move| move_input.fits(board, *legal_move))
+        .collect();
+
+    match candidates.as_slice() {
+        [] => Err(MoveInputError::Illegal),
+        [legal_move] => Ok(*legal_move),
+        [first, ..]
+            if candidates.iter().all(|candidate| {
+                candidate.is_promo()
+                    && candidate.get_src() == first.get_src()
+                    && candidate.get_dest() == first.get_dest()
+            }) =>
+        {
+            Err(MoveInputError::MissingPromotion {
+                from: first.get_src(),
+                to: first.get_dest(),
+            })
+        }
+        _ => Err(MoveInputError::Ambiguous(candidates)),
+    }
+}
+
+/// Works out why `input`, which `parse_move` found to be illegal, can't be played on `board`.
+pub fn diagnose_illegal_move(board: &Board, input: &str) -> Option<IllegalMoveReason> {
+    let move_input = read_move(input)?;
+    let us = board.turn();
+
+    if let Some(castle) = move_input.castle {
+        let castle_type = match castle {
+            Castle::KingSide => CastleType::KingSide,
+            Castle::QueenSide => CastleType::QueenSide,
+        };
+
+        return Some(if !board.can_castle(us, castle_type) {
+            IllegalMoveReason::CastlingRightsLost
+        } else if board.castle_impeded(castle_type) {
+            IllegalMoveReason::CastlingBlocked
+        } else {
+            IllegalMoveReason::CastlingThroughCheck
+        });
+    }
+
+    let to = move_input.to?;
+    let source_square = match (move_input.from_file, move_input.from_rank) {
+        (Some(file), Some(rank)) => Some(SQ(rank * 8 + file)),
+        _ => None,
+    };
+
+    if let Some(from) = source_square {
+        match board.piece_at_sq(from).player() {
+            None => return Some(IllegalMoveReason::NoPiece(from)),
+            Some(owner) if owner != us => return Some(IllegalMoveReason::OpponentsPiece(from)),
+            Some(_) => {}
+        }
+    }
+
+    // Moves that only fail because they would leave the king in check are still generated as pseudo-legal.
+    let exposes_king = board
+        .generate_pseudolegal_moves()
+        .iter()
+        .find(|pseudo_legal_move| move_input.fits(board, **pseudo_legal_move))
+        .copied();
+
+    Some(match exposes_king {
+        Some(pseudo_legal_move)
+            if board.piece_at_sq(pseudo_legal_move.get_src()).type_of() == PieceType::K =>
+        {
+            IllegalMoveReason::KingIntoCheck
+        }
+        _ if board.in_check() => IllegalMoveReason::InCheck,
+        Some(pseudo_legal_move) => IllegalMoveReason::Pinned {
+            piece: board.piece_at_sq(pseudo_legal_move.get_src()).type_of(),
+            from: pseudo_legal_move.get_src(),
+        },
+        None => IllegalMoveReason::CantReach {
+            piece: match source_square {
+                Some(from) => board.piece_at_sq(from).type_of(),
+                None => move_input.piece.unwrap_or(PieceType::P),
+            },
+            from: source_square,
+            to,
+        },
+    })
+}
+
+/// Whether the input is written in a notation we understand, whether or not it fits the position.
+/// Premoves are checked with this, as the position they will be played in doesn't exist yet.
+pub fn is_readable_move(input: &str) -> bool {
+    read_move(input).is_some()
+}
+
+fn read_move(input: &str) -> Option<MoveInput> {
+    let input = input.trim().trim_end_matches(['+', '#', '!', '?']);
+
+    match input.to_ascii_uppercase().replace('0', "O").as_str() {
+        "O-O" => {
+            return Some(MoveInput {
+                castle: Some(Castle::KingSide),
+                ..MoveInput::default()
+            })
+        }
+        "O-O-O" => {
+            return Some(MoveInput {
+                castle: Some(Castle::QueenSide),
+                ..MoveInput::default()
+            })
+        }
+        _ => {}
+    }
+
+    let mut chars: Vec<char> = input
+        .chars()
+        .filter(|c| !matches!(c, 'x' | 'X' | ':' | '-' | '='))
+        .collect();
+    let mut move_input = MoveInput::default();
+
+    // Piece letters are upper case in SAN, but a lower case one is just as clear unless it could be the b-file.
+    if let Some(piece) = chars.first().and_then(|c| piece_of(*c)) {
+        if chars[0] != 'b' {
+            move_input.piece = Some(piece);
+            chars.remove(0);
+        }
+    }
+
+    // A letter after the destination square can only be a promotion.
+    if let Some(promotion) = chars.last().and_then(|c| piece_of(*c)) {
+        if promotion == PieceType::K {
+            return None;
+        }
+        move_input.promotion = Some(promotion);
+        chars.pop();
+    }
+
+    let to_rank = rank_of(chars.pop()?)?;
+    let to_file = file_of(chars.pop()?)?;
+    move_input.to = Some(SQ(to_rank * 8 + to_file));
+
+    match chars.as_slice() {
+        [] => {}
+        [c] if file_of(*c).is_some() => move_input.from_file = file_of(*c),
+        [c] if rank_of(*c).is_some() => move_input.from_rank = rank_of(*c),
+        [file, rank] => {
+            move_input.from_file = Some(file_of(*file)?);
+            move_input.from_rank = Some(rank_of(*rank)?);
+        }
+        _ => return None,
+    }
+
+    Some(move_input)
+}
+
+impl MoveInput {
+    fn fits(&self, board: &Board, legal_move: BitMove) -> bool {
+        if let Some(castle) = self.castle {
+            return match castle {
+                Castle::KingSide => legal_move.is_king_castle(),
+                Castle::QueenSide => legal_move.is_queen_castle(),
+            };
+        }
+
+        let from = legal_move.get_src();
+        let moved_piece = board.piece_at_sq(from).type_of();
+
+        // Without a piece letter, SAN means a pawn. UCI and long algebraic give the whole source square instead.
+        let piece = match (self.piece, self.from_file, self.from_rank) {
+            (Some(piece), _, _) => piece,
+            (None, Some(_), Some(_)) => moved_piece,
+            (None, _, _) => PieceType::P,
+        };
+
+        piece == moved_piece
+            && self.to == Some(destination(legal_move))
+            && match self.from_file {
+                Some(file) => file == from.file_idx_of_sq(),
+                None => true,
+            }
+            && match self.from_rank {
+                Some(rank) => rank == from.rank_idx_of_sq(),
+                None => true,
+            }
+            && match self.promotion {
+                Some(promotion) => legal_move.is_promo() && legal_move.promo_piece() == promotion,
+                None => true,
+            }
+    }
+}
+
+/// Where the moving piece ends up. pleco encodes castling as the king capturing its own rook.
+pub(crate) fn destination(legal_move: BitMove) -> SQ {
+    let to = legal_move.get_dest();
+
+    if legal_move.is_castle() {
+        let file = if legal_move.is_king_castle() { 6 } else { 2 };
+        SQ(to.rank_idx_of_sq() * 8 + file)
+    } else {
+        to
+    }
+}
+
+/// `legal_move` in SAN, e.g. `Nbd7`, `exd5`, `O-O` or `e8=Q#`. `board` is the position it is played from.
+pub fn to_san(board: &Board, legal_move: BitMove) -> String {
+    let mut san = if legal_move.is_king_castle() {
+        "O-O".to_string()
+    } else if legal_move.is_queen_castle() {
+        "O-O-O".to_string()
+    } else {
+        let from = legal_move.get_src();
+        let piece = board.piece_at_sq(from).type_of();
+        let capture = if legal_move.is_capture() { "x" } else { "" };
+
+        let mut san = if piece == PieceType::P {
+            if legal_move.is_capture() {
+                format!("{}x", file_char(from))
+            } else {
+                String::new()
+            }
+        } else {
+            format!(
+                "{}{}{}",
+                piece.char_upper(),
+                disambiguation(board, legal_move),
+                capture
+            )
+        };
+
+        san.push_str(&legal_move.get_dest().to_string());
+        if legal_move.is_promo() {
+            san.push('=');
+            san.push(legal_move.promo_piece().char_upper());
+        }
+        san
+    };
+
+    let mut after = board.clone();
+    after.apply_move(legal_move);
+    if after.checkmate() {
+        san.push('#');
+    } else if after.in_check() {
+        san.push('+');
+    }
+
+    san
+}
+
+/// `legal_move` in SAN after its move number, e.g. `1. e4` for White or `1... e5` for Black.
+pub fn to_numbered_san(board: &Board, legal_move: BitMove) -> String {
+    let move_number = board.moves_played() / 2 + 1;
+    let dots = match board.turn() {
+        Player::White => ".",
+        Player::Black => "...",
+    };
+
+    format!("{}{} {}", move_number, dots, to_san(board, legal_move))
+}
+
+/// The file and/or rank the piece moves from, when another piece of the same kind could move to the same square.
+fn disambiguation(board: &Board, legal_move: BitMove) -> String {
+    let from = legal_move.get_src();
+    let piece = board.piece_at_sq(from).type_of();
+    let rivals: Vec<SQ> = board
+        .generate_moves()
+        .iter()
+        .filter(|other| {
+            other.get_dest() == legal_move.get_dest()
+                && other.get_src() != from
+                && board.piece_at_sq(other.get_src()).type_of() == piece
+        })
+        .map(|other| other.get_src())
+        .collect();
+
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals.iter().all(|rival| rival.file() != from.file()) {
+        file_char(from).to_string()
+    } else if rivals.iter().all(|rival| rival.rank() != from.rank()) {
+        rank_char(from).to_string()
+    } else {
+        from.to_string()
+    }
+}
+
+/// Reads a position written as FEN, e.g. to start a match from it. The move counters may be left out.
+///
+/// Besides what pleco checks, each side needs exactly one king, the side that just moved can't be in check, and
+/// castling rights need the king and rook to still be on their starting squares.
+pub fn read_fen(fen: &str) -> Result<Board, Error> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+
+    // pleco can't set a board up without both kings, counts full moves from 1, and overflows on move counters
+    // beyond what a game can reach.
+    let placement = fields.first().copied().unwrap_or_default();
+    for (king, colour) in [('K', "White"), ('k', "Black")] {
+        if placement.chars().filter(|c| *c == king).count() != 1 {
+            return Err(anyhow!("{} needs exactly one king.", colour).into());
+        }
+    }
+    if let Some(half_moves) = fields.get(4) {
+        // From 150 on, the 75-move rule would already have ended the game.
+        if half_moves.parse::<u16>().map_or(true, |n| n >= 150) {
+            return Err(anyhow!("The halfmove clock has to be between 0 and 149.").into());
+        }
+    }
+    if let Some(full_moves) = fields.get(5) {
+        if full_moves
+            .parse::<u16>()
+            .map_or(true, |n| n == 0 || n > 32768)
+        {
+            return Err(anyhow!("The full move number has to be between 1 and 32768.").into());
+        }
+    }
+
+    let board = Board::from_fen(fen).map_err(|error| {
+        anyhow!(
+            "That isn't a valid FEN: {}.",
+            format!("{:?}", error).trim_end()
+        )
+    })?;
+
+    let them = board.turn().other_player();
+    let their_king = board.king_sq(them);
+    if (board.attackers_to(their_king, board.occupied()) & board.get_occupied_player(board.turn()))
+        .is_not_empty()
+    {
+        return Err(anyhow!("The side that isn't to move can't be in check.").into());
+    }
+
+    for player in [Player::White, Player::Black] {
+        let home_rank = match player {
+            Player::White => 0,
+            Player::Black => 7,
+        };
+        for (castle_type, rook_file) in [(CastleType::KingSide, 7), (CastleType::QueenSide, 0)] {
+            let in_place = |square: u8, piece: PieceType| {
+                let found = board.piece_at_sq(SQ(home_rank * 8 + square));
+                found.player() == Some(player) && found.type_of() == piece
+            };
+            if board.can_castle(player, castle_type)
+                && !(in_place(4, PieceType::K) && in_place(rook_file, PieceType::R))
+            {
+                return Err(anyhow!(
+                    "{} can't have castling rights with the king and rook off their starting squares.",
+                    player
+                )
+                .into());
+            }
+        }
+    }
+
+    Ok(board)
+}
+
+fn file_char(square: SQ) -> char {
+    (b'a' + square.file_idx_of_sq()) as char
+}
+
+fn rank_char(square: SQ) -> char {
+    (b'1' + square.rank_idx_of_sq()) as char
+}
+
+fn piece_of(c: char) -> Option<PieceType> {
+    match c.to_ascii_uppercase() {
+        'N' => Some(PieceType::N),
+        'B' => Some(PieceType::B),
+        'R' => Some(PieceType::R),
+        'Q' => Some(PieceType::Q),
+        'K' => Some(PieceType::K),
+        _ => None,
+    }
+}
+
+fn file_of(c: char) -> Option<u8> {
+    matches!(c, 'a'..='h').then(|| c as u8 - b'a')
+}
+
+fn rank_of(c: char) -> Option<u8> {
+    matches!(c, '1'..='8').then(|| c as u8 - b'1')
+}
@@ -0,0 +1,157 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use pleco::Player;
+
+use crate::{
+    engine::{GameResult, MatchEngine},
+    notation::{parse_move, to_san},
+};
+
+/// PGN lines are kept to this length, as the standard recommends.
+const LINE_LENGTH: usize = 80;
+
+/// What a PGN needs to know about a match that its engine doesn't.
+#[derive(Debug, Clone)]
+pub struct PgnHeader {
+    pub white: String,
+    pub black: String,
+    /// When the game was played.
+    pub date: SystemTime,
+}
+
+/// The match as a PGN: the Seven Tag Roster, the time control, the starting position if it isn't the standard
+/// one, and every move in SAN with its clock reading.
+pub fn write_pgn(engine: &MatchEngine, header: &PgnHeader) -> String {
+    let result = pgn_result(engine);
+    let tags = [
+        ("Event", pgn_event(engine).to_string()),
+        ("Site", "Discord".to_string()),
+        ("Date", pgn_date(header.date)),
+        ("Round", "-".to_string()),
+        ("White", header.white.clone()),
+        ("Black", header.black.clone()),
+        ("Result", result.to_string()),
+        ("TimeControl", pgn_time_control(engine)),
+    ];
+    let set_up = engine
+        .start_fen()
+        .map(|fen| [("SetUp", "1".to_string()), ("FEN", fen.to_string())]);
+
+    let mut pgn: String = tags
+        .iter()
+        .chain(set_up.iter().flatten())
+        .map(|(name, value)| format!("[{} \"{}\"]\n", name, escape(value)))
+        .collect();
+    pgn.push('\n');
+
+    let mut tokens = movetext(engine);
+    tokens.push(result.to_string());
+    pgn.push_str(&wrap(&tokens));
+    pgn.push('\n');
+
+    pgn
+}
+
+/// The moves as they are written in the movetext, each followed by its clock comment.
+fn movetext(engine: &MatchEngine) -> Vec<String> {
+    let mut board = engine.start_board();
+    let mut tokens = Vec::new();
+
+    for (uci, clock) in engine.moves().iter().zip(engine.move_clocks()) {
+        let Ok(bit_move) = parse_move(&board, uci) else {
+            break;
+        };
+
+        let move_number = board.moves_played() / 2 + 1;
+        // Every move is followed by its clock comment, so Black's moves need their number too.
+        match board.turn() {
+            Player::White => tokens.push(format!("{}.", move_number)),
+            Player::Black => tokens.push(format!("{}...", move_number)),
+        }
+
+        tokens.push(to_san(&board, bit_move));
+        board.apply_move(bit_move);
+
+        tokens.push(format!("{{[%clk {}]}}", clock_comment(*clock)));
+    }
+
+    tokens
+}
+
+fn pgn_result(engine: &MatchEngine) -> &'static str {
+    match engine.result() {
+        Some(GameResult::Winner(Player::White)) => "1-0",
+        Some(GameResult::Winner(Player::Black)) => "0-1",
+        Some(GameResult::Draw) => "1/2-1/2",
+        Some(GameResult::Aborted) | None => "*",
+    }
+}
+
+fn pgn_event(engine: &MatchEngine) -> &'static str {
+    if engine.rated() {
+        "ThreadRook rated game"
+    } else {
+        "ThreadRook casual game"
+    }
+}
+
+/// The base time and increment in seconds, e.g. `300+5`. PGN has no way to write a delay, so delays are written
+/// as increments too.
+fn pgn_time_control(engine: &MatchEngine) -> String {
+    let time_control = engine.time_control();
+
+    format!("{}+{}", time_control.base, time_control.increment)
+}
+
+/// A clock reading as `h:mm:ss`.
+fn clock_comment(time_left: Duration) -> String {
+    let secs = time_left.as_secs();
+
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// The UTC date as `YYYY.MM.DD`.
+fn pgn_date(date: SystemTime) -> String {
+    let days = date
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs() / 86_400) as i64;
+
+    // Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Joins movetext tokens into lines of at most `LINE_LENGTH` characters.
+fn wrap(tokens: &[String]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+
+    for token in tokens {
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + token.len() <= LINE_LENGTH => {
+                line.push(' ');
+                line.push_str(token);
+            }
+            _ => lines.push(token.clone()),
+        }
+    }
+
+    lines.join("\n")
+}
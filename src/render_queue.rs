@@ -0,0 +1,132 @@
+use std::{borrow::Cow, sync::Arc};
+
+use poise::serenity_prelude::{AttachmentType, Http, Message};
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::board_style::BoardContent;
+
+/// What the pinned board, clock and legal move messages of a match should say.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PinnedContent {
+    pub board: BoardContent,
+    pub clocks: String,
+    pub legal_moves: String,
+}
+
+/// Edits the pinned messages of a match in the background, so the match never waits on Discord.
+///
+/// Only the latest content is kept. Updates that pile up while an edit is in flight, or while Discord is rate
+/// limiting the bot, are merged into a single edit per message, and messages that haven't changed aren't edited.
+#[derive(Debug)]
+pub struct RenderQueue {
+    content_tx: watch::Sender<PinnedContent>,
+    task: JoinHandle<()>,
+}
+
+impl RenderQueue {
+    /// `shown` is what the messages say right now.
+    pub fn new(
+        http: Arc<Http>,
+        mut board_message: Message,
+        mut time_info_message: Message,
+        mut legal_move_message: Message,
+        shown: PinnedContent,
+    ) -> Self {
+        let (content_tx, mut content_rx) = watch::channel(shown.clone());
+
+        let task = tokio::spawn(async move {
+            let mut shown = shown;
+
+            while content_rx.changed().await.is_ok() {
+                let content = content_rx.borrow_and_update().clone();
+
+                edit_board(&http, &mut board_message, &mut shown.board, content.board).await;
+                edit(
+                    &http,
+                    &mut time_info_message,
+                    &mut shown.clocks,
+                    content.clocks,
+                )
+                .await;
+                edit(
+                    &http,
+                    &mut legal_move_message,
+                    &mut shown.legal_moves,
+                    content.legal_moves,
+                )
+                .await;
+            }
+        });
+
+        Self { content_tx, task }
+    }
+
+    /// Queues the pinned messages to be brought up to date with `content`.
+    pub fn show(&self, content: PinnedContent) {
+        self.content_tx.send_if_modified(|queued| {
+            if *queued == content {
+                return false;
+            }
+
+            *queued = content;
+            true
+        });
+    }
+
+    /// Drops any edits that haven't been made yet.
+    pub fn close(&self) {
+        self.task.abort();
+    }
+}
+
+/// The board image as an attachment, replacing whichever image the message had before.
+pub fn board_attachment(png: &[u8]) -> AttachmentType<'static> {
+    AttachmentType::Bytes {
+        data: Cow::Owned(png.to_vec()),
+        filename: "board.png".to_string(),
+    }
+}
+
+async fn edit_board(
+    http: &Http,
+    message: &mut Message,
+    shown: &mut BoardContent,
+    board: BoardContent,
+) {
+    if *shown == board {
+        return;
+    }
+
+    let previous: Vec<_> = message
+        .attachments
+        .iter()
+        .map(|attachment| attachment.id)
+        .collect();
+    let edited = message
+        .edit(http, |m| {
+            for attachment in previous {
+                m.remove_existing_attachment(attachment);
+            }
+            match &board {
+                BoardContent::Image(png) => m.content("").attachment(board_attachment(png)),
+                BoardContent::Text(text) => m.content(text),
+            }
+        })
+        .await;
+
+    match edited {
+        Ok(()) => *shown = board,
+        Err(error) => tracing::warn!("Unable to edit pinned message {}: {}", message.id, error),
+    }
+}
+
+async fn edit(http: &Http, message: &mut Message, shown: &mut String, content: String) {
+    if *shown == content {
+        return;
+    }
+
+    match message.edit(http, |m| m.content(&content)).await {
+        Ok(()) => *shown = content,
+        Err(error) => tracing::warn!("Unable to edit pinned message {}: {}", message.id, error),
+    }
+}
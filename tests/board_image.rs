@@ -0,0 +1,63 @@
+use pleco::{Board, Player};
+use threadrook::board_image::{render_board, PieceSet};
+use tiny_skia::Pixmap;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const LIGHT_SQUARE: [u8; 3] = [240, 217, 181];
+
+/// The colour near the top right corner of the square at `column` and `row` from the top left, where neither
+/// pieces nor coordinates are drawn.
+fn square_colour(png: &[u8], column: u32, row: u32) -> [u8; 3] {
+    let pixmap = Pixmap::decode_png(png).unwrap();
+    let pixel = pixmap.pixel(column * 64 + 60, row * 64 + 3).unwrap();
+
+    [pixel.red(), pixel.green(), pixel.blue()]
+}
+
+#[test]
+fn boards_are_drawn_as_square_pngs() {
+    let png = render_board(&Board::start_pos(), Player::White, PieceSet::Classic).unwrap();
+
+    assert!(png.starts_with(PNG_SIGNATURE));
+    let pixmap = Pixmap::decode_png(&png).unwrap();
+    assert_eq!((pixmap.width(), pixmap.height()), (512, 512));
+}
+
+#[test]
+fn orientation_and_piece_set_change_the_image() {
+    let board = Board::start_pos();
+
+    let white = render_board(&board, Player::White, PieceSet::Classic).unwrap();
+    let black = render_board(&board, Player::Black, PieceSet::Classic).unwrap();
+    let letters = render_board(&board, Player::White, PieceSet::Letters).unwrap();
+
+    assert_ne!(white, black);
+    assert_ne!(white, letters);
+}
+
+#[test]
+fn the_last_move_is_highlighted() {
+    let mut board = Board::start_pos();
+    board.apply_uci_move("e2e4");
+    let same_position = Board::from_fen(&board.fen()).unwrap();
+
+    // e2 is the fifth square from the left on the second row from the bottom when White is at the bottom.
+    let highlighted = render_board(&board, Player::White, PieceSet::Classic).unwrap();
+    let plain = render_board(&same_position, Player::White, PieceSet::Classic).unwrap();
+    assert_eq!(square_colour(&plain, 4, 6), LIGHT_SQUARE);
+    assert_ne!(square_colour(&highlighted, 4, 6), LIGHT_SQUARE);
+
+    // With Black at the bottom, e2 is the fourth square from the left on the second row from the top.
+    let flipped = render_board(&board, Player::Black, PieceSet::Classic).unwrap();
+    assert_ne!(square_colour(&flipped, 3, 1), LIGHT_SQUARE);
+}
+
+#[test]
+fn a_king_in_check_is_marked() {
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/4RK2 b - - 0 1").unwrap();
+
+    let png = render_board(&board, Player::White, PieceSet::Classic).unwrap();
+    let [red, green, _] = square_colour(&png, 4, 0);
+
+    assert!(red > green + 50);
+}
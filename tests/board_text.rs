@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use pleco::{Board, Player};
+use poise::serenity_prelude::GuildId;
+use threadrook::{
+    board_style::BoardStyle,
+    board_text::{render_text_board, BoardGlyphs},
+    database::Database,
+};
+
+#[test]
+fn unicode_boards_have_coordinates() {
+    let board = Board::start_pos();
+
+    assert_eq!(
+        render_text_board(&board, Player::White, &BoardGlyphs::unicode()),
+        "```
+8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜
+7 ♟ ♟ ♟ ♟ ♟ ♟ ♟ ♟
+6 · · · · · · · ·
+5 · · · · · · · ·
+4 · · · · · · · ·
+3 · · · · · · · ·
+2 ♙ ♙ ♙ ♙ ♙ ♙ ♙ ♙
+1 ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖
+  a b c d e f g h
+```"
+    );
+}
+
+#[test]
+fn boards_flip_for_black() {
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+    let text = render_text_board(&board, Player::Black, &BoardGlyphs::unicode());
+    let lines: Vec<&str> = text.lines().collect();
+
+    assert_eq!(lines[1], "1 · · · ♔ · · · ♖");
+    assert_eq!(lines[8], "8 · · · ♚ · · · ·");
+    assert_eq!(lines[9], "  h g f e d c b a");
+}
+
+#[test]
+fn guild_emoji_replace_the_pieces_they_exist_for() {
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    let guild_emoji = HashMap::from([("wk".to_string(), "<:wk:1>".to_string())]);
+    let text = render_text_board(&board, Player::White, &BoardGlyphs::emoji(&guild_emoji));
+    let lines: Vec<&str> = text.lines().collect();
+
+    assert!(!text.starts_with("```"));
+    assert_eq!(lines.len(), 9);
+    assert_eq!(lines[7], "1\u{fe0f}\u{20e3}🟫🟨🟫🟨<:wk:1>🟨🟫🟨");
+    // Black's king has no emoji in this guild.
+    assert!(lines[0].contains('♚'));
+}
+
+#[test]
+fn board_styles_are_stored_per_guild() {
+    let database = Database::open_in_memory().unwrap();
+
+    assert_eq!(database.board_style(GuildId(1)).unwrap(), BoardStyle::Image);
+
+    database
+        .set_board_style(GuildId(1), BoardStyle::Unicode)
+        .unwrap();
+    database
+        .set_board_style(GuildId(1), BoardStyle::Emoji)
+        .unwrap();
+    database
+        .set_board_style(GuildId(2), BoardStyle::Unicode)
+        .unwrap();
+
+    assert_eq!(database.board_style(GuildId(1)).unwrap(), BoardStyle::Emoji);
+    assert_eq!(
+        database.board_style(GuildId(2)).unwrap(),
+        BoardStyle::Unicode
+    );
+}
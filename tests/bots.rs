@@ -0,0 +1,114 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{secs, settle, Harness, ALICE, BOB, GUILD_ID};
+use pleco::{Board, Player};
+use threadrook::{
+    bot::BotLevel,
+    clock::TimeControl,
+    commands::play_bot::new_bot_match_engine,
+    engine::{MatchEvent, Seat},
+};
+use tokio::time::{advance, Instant};
+
+#[test]
+fn the_stronger_levels_find_a_mate_in_one() {
+    let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+
+    for level in [BotLevel::Medium, BotLevel::Hard] {
+        assert_eq!(
+            level.choose_move(board.clone()),
+            Some("a1a8".to_string()),
+            "{}",
+            level
+        );
+    }
+}
+
+#[test]
+fn the_bot_always_plays_a_legal_move() {
+    let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    let chosen = BotLevel::Easy.choose_move(board.clone()).unwrap();
+
+    assert!(board
+        .generate_moves()
+        .iter()
+        .any(|legal_move| legal_move.stringify() == chosen));
+}
+
+#[tokio::test(start_paused = true)]
+async fn the_bot_opens_when_it_plays_white() {
+    let mut harness = Harness::new();
+    let chess_match =
+        harness.play_bot(ALICE, Player::Black, BotLevel::Easy, TimeControl::default());
+    settle().await;
+
+    assert_eq!(harness.stored_moves().len(), 1);
+    assert!(chess_match.events().iter().any(|event| matches!(
+        event,
+        MatchEvent::MoveApplied { player, .. } if player.seat == Seat::Bot(BotLevel::Easy)
+    )));
+
+    let records = harness.data.database.load_matches().unwrap();
+    assert_eq!(records[0].progress.player_two, Seat::Bot(BotLevel::Easy));
+    assert_eq!(records[0].progress.player_one_colour, Player::Black);
+}
+
+#[tokio::test(start_paused = true)]
+async fn the_clock_waits_for_the_match_to_open() {
+    let engine = new_bot_match_engine(ALICE, Player::White, BotLevel::Easy, TimeControl::default());
+    // Setting the thread up can take a while.
+    advance(Duration::from_secs(3)).await;
+
+    assert_eq!(
+        secs(engine.get_colour_timeleft(Player::White, Instant::now())),
+        300.0
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn the_bot_replies_to_every_move() {
+    let mut harness = Harness::new();
+    let _chess_match =
+        harness.play_bot(ALICE, Player::White, BotLevel::Easy, TimeControl::default());
+
+    harness.make_move(ALICE, "e4").await.unwrap();
+    assert_eq!(harness.stored_moves().len(), 2);
+    assert_eq!(harness.stored_moves()[0], "e2e4");
+
+    harness.make_move(ALICE, "d4").await.unwrap();
+    assert_eq!(harness.stored_moves().len(), 4);
+}
+
+#[tokio::test(start_paused = true)]
+async fn nobody_can_join_a_game_against_the_bot() {
+    let mut harness = Harness::new();
+    let _chess_match =
+        harness.play_bot(ALICE, Player::White, BotLevel::Easy, TimeControl::default());
+
+    assert!(harness.join_match(ALICE, BOB).await.is_err());
+    assert_eq!(harness.data.match_registry.match_of(GUILD_ID, BOB), None);
+}
+
+#[tokio::test(start_paused = true)]
+async fn the_bot_declines_draws_and_allows_takebacks() {
+    let mut harness = Harness::new();
+    let chess_match =
+        harness.play_bot(ALICE, Player::White, BotLevel::Easy, TimeControl::default());
+
+    harness.make_move(ALICE, "e4").await.unwrap();
+    harness.offer_draw(ALICE).await.unwrap();
+    assert!(chess_match
+        .events()
+        .iter()
+        .any(|event| matches!(event, MatchEvent::DrawDeclined(player) if player.bot().is_some())));
+
+    // Alice is to move again, so her move and the bot's reply are both taken back.
+    harness.request_takeback(ALICE).await.unwrap();
+    assert!(chess_match.events().iter().any(|event| matches!(
+        event,
+        MatchEvent::TakebackAccepted { player, moves: 2 } if player.user_id() == Some(ALICE)
+    )));
+    assert!(harness.stored_moves().is_empty());
+}
@@ -0,0 +1,405 @@
+//! Drives matches the way the slash commands do, with a `RecordingFrontend` standing in for Discord.
+//!
+//! Tests run under paused tokio time, so clocks and deadlines can be fast-forwarded deterministically.
+
+// Each test binary compiles this module separately and only uses part of it.
+#![allow(dead_code)]
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+
+use pleco::{Board, Player};
+use poise::serenity_prelude::{ChannelId, GuildId, MessageId, UserId};
+use threadrook::{
+    bot::BotLevel,
+    chess_match::{get_progress, ChessMatch},
+    clock::{ChessClock, TimeControl},
+    commands::{create_match::new_match_engine, play_bot::new_bot_match_engine},
+    database::{Database, MatchRecord},
+    engine::{
+        ChessPlayer, GameEndReason, GameResult, GameState, MatchEngine, MatchEvent, PlayerSlot,
+        Seat,
+    },
+    frontend::{MatchFrontend, RecordingFrontend},
+    match_registry::{MatchId, MatchRegistry},
+    Data, DiscordCommand, Error,
+};
+use tokio::{task::JoinHandle, time::Instant};
+
+pub const GUILD_ID: GuildId = GuildId(1);
+pub const ALICE: UserId = UserId(10);
+pub const BOB: UserId = UserId(11);
+pub const CAROL: UserId = UserId(12);
+pub const DAVE: UserId = UserId(13);
+
+pub struct Harness {
+    pub data: Data,
+    next_match_id: u64,
+}
+
+pub struct TestMatch {
+    pub match_id: MatchId,
+    pub frontend: RecordingFrontend,
+    pub task: JoinHandle<Result<(), Error>>,
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        Self {
+            data: Data {
+                match_registry: Arc::new(MatchRegistry::default()),
+                database: Arc::new(Database::open_in_memory().unwrap()),
+            },
+            next_match_id: 100,
+        }
+    }
+
+    /// Same as `/create_match` with the default time control, except that the creator's colour is chosen by the test.
+    pub fn create_match(&mut self, creator: UserId, creator_colour: Player) -> TestMatch {
+        self.create_timed_match(creator, creator_colour, TimeControl::default())
+    }
+
+    pub fn create_timed_match(
+        &mut self,
+        creator: UserId,
+        creator_colour: Player,
+        time_control: TimeControl,
+    ) -> TestMatch {
+        self.create_match_from(creator, creator_colour, time_control, Board::start_pos())
+    }
+
+    /// Same as `/create_match` with a `fen` to start from.
+    pub fn create_match_from(
+        &mut self,
+        creator: UserId,
+        creator_colour: Player,
+        time_control: TimeControl,
+        start: Board,
+    ) -> TestMatch {
+        let engine = new_match_engine(creator, creator_colour, time_control, start, false);
+
+        self.open(engine)
+    }
+
+    /// Same as `/create_match` with `rated` set.
+    pub fn create_rated_match(&mut self, creator: UserId, creator_colour: Player) -> TestMatch {
+        let engine = new_match_engine(
+            creator,
+            creator_colour,
+            TimeControl::default(),
+            Board::start_pos(),
+            true,
+        );
+
+        self.open(engine)
+    }
+
+    /// Same as `/play_bot`, except that the member's colour is chosen by the test.
+    pub fn play_bot(
+        &mut self,
+        member: UserId,
+        member_colour: Player,
+        level: BotLevel,
+        time_control: TimeControl,
+    ) -> TestMatch {
+        self.open(new_bot_match_engine(
+            member,
+            member_colour,
+            level,
+            time_control,
+        ))
+    }
+
+    fn open(&mut self, engine: MatchEngine) -> TestMatch {
+        let frontend = RecordingFrontend::default();
+        let (match_id, task) = self.open_with(engine, Box::new(frontend.clone()));
+
+        TestMatch {
+            match_id,
+            frontend,
+            task,
+        }
+    }
+
+    /// Same as `/create_match`, in a thread that every render fails in.
+    pub fn create_broken_match(
+        &mut self,
+        creator: UserId,
+    ) -> (MatchId, BrokenFrontend, JoinHandle<Result<(), Error>>) {
+        let engine = new_match_engine(
+            creator,
+            Player::White,
+            TimeControl::default(),
+            Board::start_pos(),
+            false,
+        );
+        let frontend = BrokenFrontend::default();
+        let (match_id, task) = self.open_with(engine, Box::new(frontend.clone()));
+
+        (match_id, frontend, task)
+    }
+
+    fn open_with(
+        &mut self,
+        engine: MatchEngine,
+        frontend: Box<dyn MatchFrontend>,
+    ) -> (MatchId, JoinHandle<Result<(), Error>>) {
+        let match_id = ChannelId(self.next_match_id);
+        self.next_match_id += 1;
+
+        let progress = get_progress(&engine, Instant::now()).unwrap();
+        let chess_match =
+            ChessMatch::open(match_id, GUILD_ID, engine, frontend, self.data.clone()).unwrap();
+
+        // Discord matches are stored along with their message ids. Any id will do here.
+        self.data
+            .database
+            .save_match(&MatchRecord {
+                match_id,
+                guild_id: GUILD_ID,
+                parent_channel_id: ChannelId(1),
+                match_thread_message_id: MessageId(1),
+                board_message_id: MessageId(2),
+                time_info_message_id: MessageId(3),
+                legal_move_message_id: MessageId(4),
+                progress,
+            })
+            .unwrap();
+
+        (match_id, tokio::spawn(chess_match.run()))
+    }
+
+    /// The moves of the only stored match.
+    pub fn stored_moves(&self) -> Vec<String> {
+        self.data.database.load_matches().unwrap()[0]
+            .progress
+            .moves
+            .clone()
+    }
+
+    /// The harness of a bot that was restarted: the same database, but no matches running until they are restored.
+    pub fn restarted(&self) -> Self {
+        Self {
+            data: Data {
+                match_registry: Arc::new(MatchRegistry::default()),
+                database: self.data.database.clone(),
+            },
+            next_match_id: self.next_match_id,
+        }
+    }
+
+    /// Picks a stored match back up, the way the bot does on startup.
+    pub async fn restore(&self, match_id: MatchId) -> TestMatch {
+        let record = self
+            .data
+            .database
+            .load_matches()
+            .unwrap()
+            .into_iter()
+            .find(|record| record.match_id == match_id)
+            .unwrap();
+        let frontend = RecordingFrontend::default();
+        let chess_match =
+            ChessMatch::reopen(&record, Box::new(frontend.clone()), self.data.clone())
+                .await
+                .unwrap();
+
+        TestMatch {
+            match_id,
+            frontend,
+            task: tokio::spawn(chess_match.run()),
+        }
+    }
+
+    pub async fn join_match(&self, waiting: UserId, joining: UserId) -> Result<(), Error> {
+        let outcome = self
+            .data
+            .match_registry
+            .join(GUILD_ID, waiting, joining)
+            .await;
+        settle().await;
+
+        outcome
+    }
+
+    pub async fn make_move(&self, user: UserId, chess_move: &str) -> Result<(), Error> {
+        self.send(user, DiscordCommand::MakeMove(user, chess_move.to_string()))
+            .await
+    }
+
+    pub async fn resign(&self, user: UserId) -> Result<(), Error> {
+        self.send(user, DiscordCommand::Resign(user)).await
+    }
+
+    pub async fn offer_draw(&self, user: UserId) -> Result<(), Error> {
+        self.send(user, DiscordCommand::OfferDraw(user)).await
+    }
+
+    pub async fn accept_draw(&self, user: UserId) -> Result<(), Error> {
+        self.send(user, DiscordCommand::AcceptDraw(user)).await
+    }
+
+    pub async fn decline_draw(&self, user: UserId) -> Result<(), Error> {
+        self.send(user, DiscordCommand::DeclineDraw(user)).await
+    }
+
+    pub async fn claim_draw(&self, user: UserId) -> Result<(), Error> {
+        self.send(user, DiscordCommand::ClaimDraw(user)).await
+    }
+
+    pub async fn request_takeback(&self, user: UserId) -> Result<(), Error> {
+        self.send(user, DiscordCommand::RequestTakeback(user)).await
+    }
+
+    /// Same as clicking a takeback button offered after `ply` moves.
+    pub async fn answer_takeback(
+        &self,
+        user: UserId,
+        ply: usize,
+        accept: bool,
+    ) -> Result<bool, Error> {
+        let outcome = self
+            .data
+            .match_registry
+            .answer_takeback(GUILD_ID, user, ply, accept)
+            .await;
+        settle().await;
+
+        outcome
+    }
+
+    /// Same as clicking a promotion button offered after `ply` moves.
+    pub async fn pick_promotion(
+        &self,
+        user: UserId,
+        ply: usize,
+        chess_move: &str,
+    ) -> Result<bool, Error> {
+        let outcome = self
+            .data
+            .match_registry
+            .pick_promotion(GUILD_ID, user, ply, chess_move.to_string())
+            .await;
+        settle().await;
+
+        outcome
+    }
+
+    pub async fn cancel_premove(&self, user: UserId) -> Result<Option<String>, Error> {
+        let outcome = self
+            .data
+            .match_registry
+            .cancel_premove(GUILD_ID, user)
+            .await;
+        settle().await;
+
+        outcome
+    }
+
+    async fn send(&self, user: UserId, command: DiscordCommand) -> Result<(), Error> {
+        let outcome = self
+            .data
+            .match_registry
+            .send_to_member(GUILD_ID, user, command)
+            .await;
+        settle().await;
+
+        outcome
+    }
+}
+
+/// A frontend whose every render fails, like a thread the bot can no longer post in.
+#[derive(Debug, Clone, Default)]
+pub struct BrokenFrontend {
+    pub closed: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl MatchFrontend for BrokenFrontend {
+    async fn render(&mut self, _engine: &MatchEngine, _event: &MatchEvent) -> Result<(), Error> {
+        Err("Missing Access".into())
+    }
+
+    async fn resume(&mut self, _engine: &MatchEngine) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<(), Error> {
+        self.closed.store(true, Ordering::SeqCst);
+
+        Ok(())
+    }
+}
+
+impl TestMatch {
+    pub fn events(&self) -> Vec<MatchEvent> {
+        self.frontend.recording().events
+    }
+
+    pub fn ending(&self) -> Option<(GameResult, GameEndReason)> {
+        self.events().into_iter().find_map(|event| match event {
+            MatchEvent::GameEnded { result, reason } => Some((result, reason)),
+            _ => None,
+        })
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.frontend.recording().closed
+    }
+}
+
+/// A started match between Alice as White and Bob as Black, from the position in `fen`.
+/// Lets engine tests set up endgames without playing up to them.
+pub fn engine_from_fen(fen: &str) -> MatchEngine {
+    let board = Board::from_fen(fen).unwrap();
+    let mut clock = ChessClock::starting_with(TimeControl::default(), board.turn());
+    clock.start(Instant::now());
+
+    MatchEngine::builder()
+        .state(GameState::Playing)
+        .join_deadline(Instant::now())
+        .player_one(
+            ChessPlayer::builder()
+                .seat(Seat::Member(ALICE))
+                .player_slot(PlayerSlot::Player1)
+                .in_game_representation(Player::White)
+                .build(),
+        )
+        .player_two(
+            ChessPlayer::builder()
+                .seat(Seat::Member(BOB))
+                .player_slot(PlayerSlot::Player2)
+                .in_game_representation(Player::Black)
+                .build(),
+        )
+        .clock(clock)
+        .positions(vec![board.zobrist()])
+        .board(board)
+        .rated(false)
+        .build()
+}
+
+/// The result and reason of the first `GameEnded` among `events`.
+pub fn ending_of(events: &[MatchEvent]) -> Option<(GameResult, GameEndReason)> {
+    events.iter().find_map(|event| match event {
+        MatchEvent::GameEnded { result, reason } => Some((*result, *reason)),
+        _ => None,
+    })
+}
+
+/// A clock reading in seconds, rounded to tenths to hide the milliseconds that `settle` lets pass.
+pub fn secs(time_left: Duration) -> f64 {
+    (time_left.as_secs_f64() * 10.0).round() / 10.0
+}
+
+/// Gives every task the chance to handle what was sent to it, while letting a millisecond of paused time pass.
+pub async fn settle() {
+    tokio::time::sleep(Duration::from_millis(1)).await;
+}
@@ -0,0 +1,212 @@
+mod common;
+
+use common::{ending_of, engine_from_fen, Harness, TestMatch, ALICE, BOB};
+use pleco::Player;
+use threadrook::engine::{GameEndReason, GameResult, MatchEvent};
+use tokio::time::Instant;
+
+async fn started_match(harness: &mut Harness) -> TestMatch {
+    let chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    chess_match
+}
+
+/// Both knights hop out and back, bringing the starting position back once.
+async fn shuffle_knights(harness: &Harness) {
+    for (user, chess_move) in [
+        (ALICE, "g1f3"),
+        (BOB, "g8f6"),
+        (ALICE, "f3g1"),
+        (BOB, "f6g8"),
+    ] {
+        harness.make_move(user, chess_move).await.unwrap();
+    }
+}
+
+fn offer_lapsed(chess_match: &TestMatch) -> bool {
+    chess_match
+        .events()
+        .iter()
+        .any(|event| matches!(event, MatchEvent::DrawOfferLapsed(_)))
+}
+
+#[tokio::test(start_paused = true)]
+async fn accepted_draw_offer_ends_the_match() {
+    let mut harness = Harness::new();
+    let chess_match = started_match(&mut harness).await;
+
+    harness.offer_draw(ALICE).await.unwrap();
+    harness.accept_draw(BOB).await.unwrap();
+
+    assert_eq!(
+        chess_match.ending(),
+        Some((GameResult::Draw, GameEndReason::Agreement))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn offering_a_draw_back_agrees_to_it() {
+    let mut harness = Harness::new();
+    let chess_match = started_match(&mut harness).await;
+
+    harness.offer_draw(BOB).await.unwrap();
+    harness.offer_draw(ALICE).await.unwrap();
+
+    assert_eq!(
+        chess_match.ending(),
+        Some((GameResult::Draw, GameEndReason::Agreement))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn declined_or_missing_offers_cannot_be_accepted() {
+    let mut harness = Harness::new();
+    let chess_match = started_match(&mut harness).await;
+
+    harness.accept_draw(BOB).await.unwrap();
+    harness.offer_draw(ALICE).await.unwrap();
+    harness.accept_draw(ALICE).await.unwrap();
+    harness.decline_draw(BOB).await.unwrap();
+    harness.accept_draw(BOB).await.unwrap();
+
+    let events = chess_match.events();
+    assert_eq!(chess_match.ending(), None);
+    assert_eq!(
+        events
+            .iter()
+            .filter(|event| matches!(event, MatchEvent::NoDrawOffer(_)))
+            .count(),
+        3
+    );
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, MatchEvent::DrawDeclined(player) if player.colour() == Player::Black)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn offer_lapses_when_the_offering_side_moves_again() {
+    let mut harness = Harness::new();
+    let chess_match = started_match(&mut harness).await;
+
+    // Offered along with White's first move, so it stands until White's second.
+    harness.offer_draw(ALICE).await.unwrap();
+    harness.make_move(ALICE, "e2e4").await.unwrap();
+    harness.make_move(BOB, "e7e5").await.unwrap();
+    assert!(!offer_lapsed(&chess_match));
+
+    harness.make_move(ALICE, "g1f3").await.unwrap();
+    assert!(offer_lapsed(&chess_match));
+
+    harness.accept_draw(BOB).await.unwrap();
+    assert_eq!(chess_match.ending(), None);
+}
+
+#[tokio::test(start_paused = true)]
+async fn offer_made_on_the_opponents_turn_lapses_at_the_next_move() {
+    let mut harness = Harness::new();
+    let chess_match = started_match(&mut harness).await;
+
+    harness.make_move(ALICE, "e2e4").await.unwrap();
+    harness.offer_draw(ALICE).await.unwrap();
+    harness.make_move(BOB, "e7e5").await.unwrap();
+    assert!(!offer_lapsed(&chess_match));
+
+    harness.make_move(ALICE, "g1f3").await.unwrap();
+    assert!(offer_lapsed(&chess_match));
+}
+
+#[tokio::test(start_paused = true)]
+async fn fivefold_repetition_ends_the_match() {
+    let mut harness = Harness::new();
+    let chess_match = started_match(&mut harness).await;
+
+    for _ in 0..3 {
+        shuffle_knights(&harness).await;
+    }
+    assert_eq!(chess_match.ending(), None);
+
+    shuffle_knights(&harness).await;
+
+    assert_eq!(
+        chess_match.ending(),
+        Some((GameResult::Draw, GameEndReason::FivefoldRepetition))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn threefold_repetition_can_be_claimed() {
+    let mut harness = Harness::new();
+    let chess_match = started_match(&mut harness).await;
+
+    shuffle_knights(&harness).await;
+    harness.claim_draw(ALICE).await.unwrap();
+    assert!(chess_match
+        .events()
+        .contains(&MatchEvent::DrawClaimRejected(ALICE)));
+    assert_eq!(chess_match.ending(), None);
+
+    shuffle_knights(&harness).await;
+    harness.claim_draw(BOB).await.unwrap();
+
+    assert_eq!(
+        chess_match.ending(),
+        Some((GameResult::Draw, GameEndReason::ThreefoldRepetition))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn bare_kings_are_a_draw() {
+    let mut engine = engine_from_fen("8/8/8/4k3/8/8/3nK3/8 w - - 0 1");
+
+    let events = engine.make_move(ALICE, "e2d2", Instant::now());
+
+    assert_eq!(
+        ending_of(&events),
+        Some((GameResult::Draw, GameEndReason::InsufficientMaterial))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn bishops_all_on_one_square_colour_are_a_draw() {
+    let mut opposite_colours = engine_from_fen("8/8/2b1k3/8/8/8/3nKB2/8 w - - 0 1");
+    let events = opposite_colours.make_move(ALICE, "e2d2", Instant::now());
+    assert_eq!(ending_of(&events), None);
+
+    let mut same_colour = engine_from_fen("8/8/4k3/2b5/8/8/3nKB2/8 w - - 0 1");
+    let events = same_colour.make_move(ALICE, "e2d2", Instant::now());
+    assert_eq!(
+        ending_of(&events),
+        Some((GameResult::Draw, GameEndReason::InsufficientMaterial))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn seventy_five_move_rule_ends_the_match() {
+    let mut engine = engine_from_fen("8/8/8/4k3/8/8/4K3/4R3 w - - 149 100");
+
+    let events = engine.make_move(ALICE, "e1a1", Instant::now());
+
+    assert_eq!(
+        ending_of(&events),
+        Some((GameResult::Draw, GameEndReason::SeventyFiveMoveRule))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn fifty_move_rule_can_be_claimed() {
+    let mut engine = engine_from_fen("8/8/8/4k3/8/8/4K3/4R3 w - - 99 100");
+
+    assert_eq!(
+        engine.claim_draw(BOB),
+        vec![MatchEvent::DrawClaimRejected(BOB)]
+    );
+
+    engine.make_move(ALICE, "e1a1", Instant::now());
+    let events = engine.claim_draw(BOB);
+
+    assert_eq!(
+        ending_of(&events),
+        Some((GameResult::Draw, GameEndReason::FiftyMoveRule))
+    );
+}
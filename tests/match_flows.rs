@@ -0,0 +1,359 @@
+mod common;
+
+use std::{sync::atomic::Ordering, time::Duration};
+
+use common::{engine_from_fen, secs, Harness, ALICE, BOB, CAROL, DAVE, GUILD_ID};
+use pleco::{Board, PieceType, Player, SQ};
+use poise::serenity_prelude::ChannelId;
+use threadrook::{
+    clock::TimeControl,
+    commands::promotion::{parse_promotion_button_id, promotion_button_id},
+    engine::{GameEndReason, GameResult, MatchEvent, Seat},
+    notation::IllegalMoveReason,
+    DiscordCommand,
+};
+use tokio::time::{sleep, Instant};
+
+#[tokio::test(start_paused = true)]
+async fn fools_mate_ends_the_match_and_frees_both_players() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+
+    harness.join_match(ALICE, BOB).await.unwrap();
+    for (player, chess_move) in [
+        (ALICE, "f2f3"),
+        (BOB, "e7e5"),
+        (ALICE, "g2g4"),
+        (BOB, "d8h4"),
+    ] {
+        harness.make_move(player, chess_move).await.unwrap();
+    }
+
+    assert_eq!(
+        chess_match.ending(),
+        Some((GameResult::Winner(Player::Black), GameEndReason::Checkmate))
+    );
+    assert_eq!(harness.data.match_registry.match_of(GUILD_ID, ALICE), None);
+    assert_eq!(harness.data.match_registry.match_of(GUILD_ID, BOB), None);
+}
+
+#[tokio::test(start_paused = true)]
+async fn thread_is_closed_thirty_seconds_after_the_match_ends() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    harness.resign(BOB).await.unwrap();
+    assert_eq!(
+        chess_match.ending(),
+        Some((
+            GameResult::Winner(Player::White),
+            GameEndReason::Resignation(Player::Black)
+        ))
+    );
+
+    sleep(Duration::from_secs(29)).await;
+    assert!(!chess_match.is_closed());
+
+    sleep(Duration::from_secs(2)).await;
+    assert!(chess_match.is_closed());
+    assert!(chess_match.task.await.unwrap().is_ok());
+    assert!(harness.data.database.load_matches().unwrap().is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn matches_carry_on_when_rendering_fails() {
+    let mut harness = Harness::new();
+    let (match_id, frontend, task) = harness.create_broken_match(ALICE);
+    harness.join_match(ALICE, BOB).await.unwrap();
+    harness.make_move(ALICE, "e4").await.unwrap();
+    harness.make_move(BOB, "e5").await.unwrap();
+
+    assert!(!task.is_finished());
+    assert!(!frontend.closed.load(Ordering::SeqCst));
+    assert_eq!(
+        harness.data.match_registry.match_of(GUILD_ID, BOB),
+        Some(match_id)
+    );
+    assert_eq!(harness.stored_moves(), vec!["e2e4", "e7e5"]);
+
+    harness.resign(BOB).await.unwrap();
+    assert!(task.await.unwrap().is_ok());
+    assert!(frontend.closed.load(Ordering::SeqCst));
+    assert!(harness.data.database.load_matches().unwrap().is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn resigning_before_anyone_joins_cancels_the_match() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::Black);
+
+    harness.resign(ALICE).await.unwrap();
+
+    assert_eq!(
+        chess_match.ending(),
+        Some((GameResult::Aborted, GameEndReason::Cancelled))
+    );
+    assert!(harness.join_match(ALICE, BOB).await.is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn match_is_aborted_when_nobody_joins_in_time() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+
+    sleep(Duration::from_millis(89_500)).await;
+    assert_eq!(chess_match.ending(), None);
+
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(
+        chess_match.ending(),
+        Some((GameResult::Aborted, GameEndReason::NoOpponent))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn flag_falls_once_the_acting_player_runs_out_of_time() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::Black);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    sleep(Duration::from_millis(299_500)).await;
+    assert_eq!(chess_match.ending(), None);
+
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(
+        chess_match.ending(),
+        Some((
+            GameResult::Winner(Player::Black),
+            GameEndReason::Timeout(Player::White)
+        ))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn members_can_only_join_one_open_match_that_is_not_their_own() {
+    let mut harness = Harness::new();
+    let _alice_match = harness.create_match(ALICE, Player::White);
+    let _carol_match = harness.create_match(CAROL, Player::White);
+
+    assert!(harness.join_match(ALICE, ALICE).await.is_err());
+    harness.join_match(ALICE, BOB).await.unwrap();
+    assert!(harness.join_match(CAROL, BOB).await.is_err());
+    assert!(harness.join_match(ALICE, DAVE).await.is_err());
+    assert!(harness.join_match(DAVE, CAROL).await.is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn members_setting_up_a_match_are_held_out_of_every_other_match() {
+    let mut harness = Harness::new();
+    let registry = harness.data.match_registry.clone();
+    let _carol_match = harness.create_match(CAROL, Player::White);
+
+    let reservation = registry.reserve(GUILD_ID, ALICE).unwrap();
+    assert!(registry.reserve(GUILD_ID, ALICE).is_err());
+    assert!(harness.join_match(CAROL, ALICE).await.is_err());
+    assert!(registry.reserve(GUILD_ID, CAROL).is_err());
+
+    // Registering the reserved member's own match still works.
+    let _alice_match = harness.create_match(ALICE, Player::White);
+    drop(reservation);
+    assert!(registry.match_of(GUILD_ID, ALICE).is_some());
+    assert!(registry.reserve(GUILD_ID, ALICE).is_err());
+
+    let reservation = registry.reserve(GUILD_ID, DAVE).unwrap();
+    drop(reservation);
+    harness.join_match(CAROL, DAVE).await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn illegal_moves_leave_the_board_alone() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    harness.make_move(ALICE, "e2e5").await.unwrap();
+
+    let events = chess_match.events();
+    assert!(events.contains(&MatchEvent::IllegalMove {
+        chess_move: "e2e5".to_string(),
+        reason: IllegalMoveReason::CantReach {
+            piece: PieceType::P,
+            from: Some(SQ::E2),
+            to: SQ::E5,
+        },
+    }));
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, MatchEvent::MoveApplied { .. })));
+}
+
+#[tokio::test(start_paused = true)]
+async fn commands_from_members_without_a_match_fail_straight_away() {
+    let harness = Harness::new();
+
+    assert!(harness.make_move(CAROL, "e2e4").await.is_err());
+    assert!(harness.resign(CAROL).await.is_err());
+    assert!(harness
+        .data
+        .match_registry
+        .send_to_member(GUILD_ID, CAROL, DiscordCommand::Resign(CAROL))
+        .await
+        .is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn nothing_is_rendered_between_moves() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+
+    sleep(Duration::from_secs(30)).await;
+    assert!(chess_match.events().is_empty());
+
+    harness.join_match(ALICE, BOB).await.unwrap();
+    harness.make_move(ALICE, "e2e4").await.unwrap();
+    let rendered = chess_match.events().len();
+
+    sleep(Duration::from_secs(60)).await;
+    assert_eq!(chess_match.events().len(), rendered);
+}
+
+#[tokio::test(start_paused = true)]
+async fn moves_and_clocks_are_stored_after_every_move() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    harness.make_move(ALICE, "e2e4").await.unwrap();
+    sleep(Duration::from_secs(3)).await;
+    harness.make_move(BOB, "e7e5").await.unwrap();
+
+    let records = harness.data.database.load_matches().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].match_id, chess_match.match_id);
+    assert_eq!(records[0].progress.player_two, Seat::Member(BOB));
+    assert_eq!(records[0].progress.moves, vec!["e2e4", "e7e5"]);
+
+    let clock = &records[0].progress.clock;
+    assert_eq!(secs(clock.remaining(Player::White, Instant::now())), 300.0);
+    assert_eq!(secs(clock.remaining(Player::Black, Instant::now())), 297.0);
+
+    let move_clocks: Vec<f64> = records[0]
+        .progress
+        .move_clocks
+        .iter()
+        .map(|clock| secs(*clock))
+        .collect();
+    assert_eq!(move_clocks, vec![300.0, 297.0]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn only_the_player_to_move_is_offered_legal_moves() {
+    let mut harness = Harness::new();
+    let _chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    let registry = &harness.data.match_registry;
+    let legal_moves = registry.legal_moves(GUILD_ID, ALICE).await.unwrap();
+    assert_eq!(legal_moves.len(), 20);
+    assert!(legal_moves
+        .iter()
+        .any(|legal_move| legal_move.san == "Nf3" && legal_move.uci == "g1f3"));
+    assert!(registry
+        .legal_moves(GUILD_ID, BOB)
+        .await
+        .unwrap()
+        .is_empty());
+
+    harness.make_move(ALICE, "e4").await.unwrap();
+
+    assert!(registry
+        .legal_moves(GUILD_ID, ALICE)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(registry.legal_moves(GUILD_ID, BOB).await.unwrap().len(), 20);
+    assert!(registry.legal_moves(GUILD_ID, CAROL).await.is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn promotions_wait_for_a_piece_while_the_clock_runs() {
+    let mut engine = engine_from_fen("8/p3P3/8/8/8/2k5/8/4K3 w - - 0 1");
+
+    let events = engine.make_move(ALICE, "e8", Instant::now());
+    assert!(matches!(
+        events.as_slice(),
+        [MatchEvent::PromotionNeeded { chess_move, .. }] if chess_move == "e7e8"
+    ));
+
+    sleep(Duration::from_secs(10)).await;
+    let (user, ply, chess_move) =
+        parse_promotion_button_id(&promotion_button_id(ALICE, 0, "e7e8", PieceType::N)).unwrap();
+    assert_eq!(ply, 0);
+    let events = engine.make_move(user, &chess_move, Instant::now());
+
+    assert!(matches!(
+        events.as_slice(),
+        [MatchEvent::MoveApplied { san, .. }] if san == "1. e8=N"
+    ));
+    assert_eq!(
+        secs(engine.get_colour_timeleft(Player::White, Instant::now())),
+        290.0
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn promotion_buttons_are_ignored_once_another_move_is_played() {
+    let mut harness = Harness::new();
+    let start = Board::from_fen("8/p3P3/8/8/8/2k5/8/4K3 w - - 0 1").unwrap();
+    let chess_match =
+        harness.create_match_from(ALICE, Player::White, TimeControl::default(), start);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    harness.make_move(ALICE, "e8").await.unwrap();
+    harness.make_move(ALICE, "Kf2").await.unwrap();
+    harness.make_move(BOB, "a5").await.unwrap();
+
+    // The buttons were offered before any move was played.
+    assert!(!harness.pick_promotion(ALICE, 0, "e7e8q").await.unwrap());
+    assert!(!chess_match
+        .events()
+        .iter()
+        .any(|event| matches!(event, MatchEvent::MoveApplied { san, .. } if san.contains("e8="))));
+
+    assert!(harness.pick_promotion(ALICE, 2, "e7e8q").await.unwrap());
+    assert!(chess_match
+        .events()
+        .iter()
+        .any(|event| matches!(event, MatchEvent::MoveApplied { san, .. } if san == "2. e8=Q")));
+}
+
+#[tokio::test(start_paused = true)]
+async fn spectators_can_view_the_board_from_the_match_thread() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+    harness.make_move(ALICE, "e4").await.unwrap();
+
+    let registry = &harness.data.match_registry;
+    let elsewhere = ChannelId(1);
+
+    let alice_view = registry
+        .view_board(GUILD_ID, ALICE, elsewhere)
+        .await
+        .unwrap();
+    assert_eq!(alice_view.colour, Some(Player::White));
+    assert_eq!(alice_view.board.moves_played(), 1);
+
+    let carol_view = registry
+        .view_board(GUILD_ID, CAROL, chess_match.match_id)
+        .await
+        .unwrap();
+    assert_eq!(carol_view.colour, None);
+    assert_eq!(carol_view.board.fen(), alice_view.board.fen());
+
+    assert!(registry
+        .view_board(GUILD_ID, CAROL, elsewhere)
+        .await
+        .is_err());
+}
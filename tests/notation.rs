@@ -0,0 +1,240 @@
+use pleco::{BitMove, Board, PieceType, SQ};
+use threadrook::notation::{
+    diagnose_illegal_move, parse_move, to_numbered_san, to_san, IllegalMoveReason, MoveInputError,
+};
+
+fn uci(board: &Board, input: &str) -> Result<String, MoveInputError> {
+    parse_move(board, input).map(BitMove::stringify)
+}
+
+#[test]
+fn san_moves_are_understood() {
+    let board = Board::start_pos();
+
+    assert_eq!(uci(&board, "e4"), Ok("e2e4".to_string()));
+    assert_eq!(uci(&board, "Nf3"), Ok("g1f3".to_string()));
+    assert_eq!(uci(&board, "nc3"), Ok("b1c3".to_string()));
+}
+
+#[test]
+fn long_algebraic_and_uci_moves_are_understood() {
+    let board = Board::start_pos();
+
+    assert_eq!(uci(&board, "e2e4"), Ok("e2e4".to_string()));
+    assert_eq!(uci(&board, "e2-e4"), Ok("e2e4".to_string()));
+    assert_eq!(uci(&board, "Ng1-f3"), Ok("g1f3".to_string()));
+}
+
+#[test]
+fn captures_checks_and_mates_are_understood() {
+    let board =
+        Board::from_fen("rnbqkbnr/ppp2ppp/8/3pp3/4P3/5Q2/PPPP1PPP/RNB1KBNR w KQkq - 0 3").unwrap();
+
+    assert_eq!(uci(&board, "exd5"), Ok("e4d5".to_string()));
+    assert_eq!(uci(&board, "ed5"), Ok("e4d5".to_string()));
+    assert_eq!(uci(&board, "Qxf7+"), Ok("f3f7".to_string()));
+    assert_eq!(uci(&board, "Qf7#"), Ok("f3f7".to_string()));
+}
+
+#[test]
+fn both_castling_spellings_are_understood() {
+    let board = Board::from_fen("r3k2r/pppqbppp/2np1n2/4p3/4P3/2NP1N2/PPPQBPPP/R3K2R w KQkq - 0 1")
+        .unwrap();
+
+    assert_eq!(uci(&board, "O-O"), Ok("e1g1".to_string()));
+    assert_eq!(uci(&board, "0-0"), Ok("e1g1".to_string()));
+    assert_eq!(uci(&board, "O-O-O"), Ok("e1c1".to_string()));
+    assert_eq!(uci(&board, "0-0-0+"), Ok("e1c1".to_string()));
+    assert_eq!(uci(&board, "e1g1"), Ok("e1g1".to_string()));
+}
+
+#[test]
+fn promotions_are_understood() {
+    let board = Board::from_fen("3r4/4P3/8/8/8/2k5/8/4K3 w - - 0 1").unwrap();
+
+    assert_eq!(uci(&board, "e8=Q"), Ok("e7e8q".to_string()));
+    assert_eq!(uci(&board, "e8N"), Ok("e7e8n".to_string()));
+    assert_eq!(uci(&board, "exd8=R+"), Ok("e7d8r".to_string()));
+    assert_eq!(uci(&board, "e7e8b"), Ok("e7e8b".to_string()));
+}
+
+#[test]
+fn ambiguous_moves_list_every_candidate() {
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1").unwrap();
+
+    let Err(MoveInputError::Ambiguous(candidates)) = parse_move(&board, "Nd2") else {
+        panic!("Nd2 should be ambiguous");
+    };
+    let mut candidates: Vec<String> = candidates.into_iter().map(BitMove::stringify).collect();
+    candidates.sort();
+    assert_eq!(candidates, ["b1d2", "f1d2"]);
+
+    assert_eq!(uci(&board, "Nbd2"), Ok("b1d2".to_string()));
+    assert_eq!(uci(&board, "Nfd2"), Ok("f1d2".to_string()));
+}
+
+#[test]
+fn illegal_and_unreadable_moves_are_told_apart() {
+    let board = Board::start_pos();
+
+    assert_eq!(uci(&board, "e5"), Err(MoveInputError::Illegal));
+    assert_eq!(uci(&board, "Ke2"), Err(MoveInputError::Illegal));
+    assert_eq!(uci(&board, "hello"), Err(MoveInputError::Unreadable));
+    assert_eq!(uci(&board, "Nz9"), Err(MoveInputError::Unreadable));
+    assert_eq!(uci(&board, ""), Err(MoveInputError::Unreadable));
+}
+
+fn san(board: &Board, uci: &str) -> String {
+    let legal_move = parse_move(board, uci).unwrap();
+    to_san(board, legal_move)
+}
+
+#[test]
+fn moves_are_written_in_san() {
+    let board = Board::start_pos();
+    assert_eq!(san(&board, "e2e4"), "e4");
+    assert_eq!(san(&board, "g1f3"), "Nf3");
+
+    let board = Board::from_fen("r3k2r/pppqbppp/2np1n2/4p3/4P3/2NP1N2/PPPQBPPP/R3K2R w KQkq - 0 1")
+        .unwrap();
+    assert_eq!(san(&board, "e1g1"), "O-O");
+    assert_eq!(san(&board, "e1c1"), "O-O-O");
+    assert_eq!(san(&board, "f3e5"), "Nxe5");
+
+    let board = Board::from_fen("3r4/4P3/8/8/8/2k5/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(san(&board, "e7d8q"), "exd8=Q");
+}
+
+#[test]
+fn san_names_the_source_only_when_needed() {
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1").unwrap();
+    assert_eq!(san(&board, "b1d2"), "Nbd2");
+    assert_eq!(san(&board, "b1c3"), "Nc3");
+
+    let board = Board::from_fen("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1").unwrap();
+    assert_eq!(san(&board, "a1a3"), "R1a3");
+
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/Q1Q1K2Q w - - 0 1").unwrap();
+    assert_eq!(san(&board, "a1b2"), "Qab2");
+}
+
+#[test]
+fn san_marks_checks_and_mates() {
+    let board =
+        Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 4 4")
+            .unwrap();
+    assert_eq!(san(&board, "f3f7"), "Qxf7#");
+
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+    assert_eq!(san(&board, "a1a8"), "Ra8+");
+}
+
+#[test]
+fn numbered_san_counts_full_moves() {
+    let mut board = Board::start_pos();
+    let e4 = parse_move(&board, "e4").unwrap();
+    assert_eq!(to_numbered_san(&board, e4), "1. e4");
+
+    board.apply_move(e4);
+    let e5 = parse_move(&board, "e5").unwrap();
+    assert_eq!(to_numbered_san(&board, e5), "1... e5");
+}
+
+fn diagnose(fen: &str, input: &str) -> Option<IllegalMoveReason> {
+    let board = Board::from_fen(fen).unwrap();
+    assert_eq!(parse_move(&board, input), Err(MoveInputError::Illegal));
+
+    diagnose_illegal_move(&board, input)
+}
+
+#[test]
+fn moves_from_the_wrong_square_are_explained() {
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    assert_eq!(
+        diagnose(start, "e3e4"),
+        Some(IllegalMoveReason::NoPiece(SQ::E3))
+    );
+    assert_eq!(
+        diagnose(start, "e7e5"),
+        Some(IllegalMoveReason::OpponentsPiece(SQ::E7))
+    );
+    assert_eq!(
+        diagnose(start, "Ng1-g3"),
+        Some(IllegalMoveReason::CantReach {
+            piece: PieceType::N,
+            from: Some(SQ::G1),
+            to: SQ::G3,
+        })
+    );
+    assert_eq!(
+        diagnose(start, "Qd4"),
+        Some(IllegalMoveReason::CantReach {
+            piece: PieceType::Q,
+            from: None,
+            to: SQ::D4,
+        })
+    );
+}
+
+#[test]
+fn pins_and_checks_are_explained() {
+    // The knight on d2 is pinned by the bishop on b4.
+    let pinned = "4k3/8/8/8/1b6/8/3N4/4K3 w - - 0 1";
+    assert_eq!(
+        diagnose(pinned, "Nf3"),
+        Some(IllegalMoveReason::Pinned {
+            piece: PieceType::N,
+            from: SQ::D2,
+        })
+    );
+
+    // The rook on e2 checks the white king and covers the second rank.
+    let in_check = "4k3/8/8/8/8/8/4r3/R3K3 w Q - 0 1";
+    assert_eq!(diagnose(in_check, "Ra7"), Some(IllegalMoveReason::InCheck));
+    assert_eq!(
+        diagnose(in_check, "Kd2"),
+        Some(IllegalMoveReason::KingIntoCheck)
+    );
+}
+
+#[test]
+fn castling_is_explained() {
+    let no_rights = "r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1";
+    assert_eq!(
+        diagnose(no_rights, "O-O"),
+        Some(IllegalMoveReason::CastlingRightsLost)
+    );
+
+    let blocked = "r3k2r/8/8/8/8/8/8/RN2K2R w KQkq - 0 1";
+    assert_eq!(
+        diagnose(blocked, "O-O-O"),
+        Some(IllegalMoveReason::CastlingBlocked)
+    );
+
+    let through_check = "r3k2r/8/8/8/8/8/5r2/R3K2R w KQ - 0 1";
+    assert_eq!(
+        diagnose(through_check, "0-0"),
+        Some(IllegalMoveReason::CastlingThroughCheck)
+    );
+}
+
+#[test]
+fn promotions_must_name_a_piece() {
+    let board = Board::from_fen("8/4P3/8/8/8/2k5/8/4K3 w - - 0 1").unwrap();
+
+    assert_eq!(
+        parse_move(&board, "e8"),
+        Err(MoveInputError::MissingPromotion {
+            from: SQ::E7,
+            to: SQ::E8,
+        })
+    );
+    assert_eq!(
+        parse_move(&board, "e7e8"),
+        Err(MoveInputError::MissingPromotion {
+            from: SQ::E7,
+            to: SQ::E8,
+        })
+    );
+}
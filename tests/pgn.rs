@@ -0,0 +1,71 @@
+mod common;
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use common::{engine_from_fen, ALICE, BOB};
+use threadrook::pgn::{write_pgn, PgnHeader};
+use tokio::time::{advance, Instant};
+
+const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+fn header() -> PgnHeader {
+    PgnHeader {
+        white: "Alice".to_string(),
+        black: "Bob".to_string(),
+        // 2023-03-14
+        date: UNIX_EPOCH + Duration::from_secs(1_678_800_000),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn finished_games_are_written_with_clocks_and_result() {
+    let mut engine = engine_from_fen(START);
+
+    for (user, chess_move, think) in [
+        (ALICE, "f3", 2),
+        (BOB, "e5", 3),
+        (ALICE, "g4", 65),
+        (BOB, "Qh4", 1),
+    ] {
+        advance(Duration::from_secs(think)).await;
+        engine.make_move(user, chess_move, Instant::now());
+    }
+
+    assert_eq!(
+        write_pgn(&engine, &header()),
+        "[Event \"ThreadRook casual game\"]
+[Site \"Discord\"]
+[Date \"2023.03.14\"]
+[Round \"-\"]
+[White \"Alice\"]
+[Black \"Bob\"]
+[Result \"0-1\"]
+[TimeControl \"300+0\"]
+
+1. f3 {[%clk 0:04:58]} 1... e5 {[%clk 0:04:57]} 2. g4 {[%clk 0:03:53]} 2... Qh4#
+{[%clk 0:04:56]} 0-1
+"
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn games_in_progress_have_no_result() {
+    let mut engine = engine_from_fen(START);
+    engine.make_move(ALICE, "e4", Instant::now());
+
+    let pgn = write_pgn(&engine, &header());
+
+    assert!(pgn.contains("[Result \"*\"]"));
+    assert!(pgn.ends_with("1. e4 {[%clk 0:05:00]} *\n"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn names_are_escaped() {
+    let engine = engine_from_fen(START);
+    let header = PgnHeader {
+        white: "\"Quoted\"".to_string(),
+        ..header()
+    };
+
+    assert!(write_pgn(&engine, &header).contains("[White \"\\\"Quoted\\\"\"]"));
+}
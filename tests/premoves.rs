@@ -0,0 +1,85 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{secs, Harness, ALICE, BOB};
+use pleco::Player;
+use threadrook::engine::MatchEvent;
+use tokio::time::{sleep, Instant};
+
+#[tokio::test(start_paused = true)]
+async fn premoves_are_played_as_soon_as_the_opponent_moves_at_no_clock_cost() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    harness.make_move(BOB, "e5").await.unwrap();
+    sleep(Duration::from_secs(2)).await;
+    harness.make_move(ALICE, "e4").await.unwrap();
+
+    assert_eq!(harness.stored_moves(), vec!["e2e4", "e7e5"]);
+    assert!(!chess_match
+        .events()
+        .iter()
+        .any(|event| matches!(event, MatchEvent::IllegalMove { .. })));
+
+    let records = harness.data.database.load_matches().unwrap();
+    let clock = &records[0].progress.clock;
+    assert_eq!(clock.to_move(), Player::White);
+    assert_eq!(secs(clock.remaining(Player::Black, Instant::now())), 300.0);
+    assert_eq!(secs(clock.remaining(Player::White, Instant::now())), 298.0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn premoves_that_turn_out_illegal_are_dropped() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    // The bishop on f8 is still shut in by the e7 pawn.
+    harness.make_move(BOB, "Bb4").await.unwrap();
+    harness.make_move(ALICE, "d4").await.unwrap();
+
+    assert_eq!(harness.stored_moves(), vec!["d2d4"]);
+    assert!(chess_match.events().contains(&MatchEvent::PremoveDropped {
+        user_id: BOB,
+        chess_move: "Bb4".to_string(),
+    }));
+}
+
+#[tokio::test(start_paused = true)]
+async fn premoves_that_are_not_moves_at_all_are_refused_straight_away() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    for input in ["hello", "Nf9"] {
+        harness.make_move(BOB, input).await.unwrap();
+        assert!(chess_match
+            .events()
+            .contains(&MatchEvent::UnreadableMove(input.to_string())));
+    }
+    assert_eq!(harness.cancel_premove(BOB).await.unwrap(), None);
+}
+
+#[tokio::test(start_paused = true)]
+async fn the_latest_premove_counts_and_can_be_cancelled() {
+    let mut harness = Harness::new();
+    let _chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    harness.make_move(BOB, "e5").await.unwrap();
+    harness.make_move(BOB, "d5").await.unwrap();
+    harness.make_move(ALICE, "e4").await.unwrap();
+    assert_eq!(harness.stored_moves(), vec!["e2e4", "d7d5"]);
+
+    harness.make_move(BOB, "dxe4").await.unwrap();
+    assert_eq!(
+        harness.cancel_premove(BOB).await.unwrap(),
+        Some("dxe4".to_string())
+    );
+    assert_eq!(harness.cancel_premove(BOB).await.unwrap(), None);
+
+    harness.make_move(ALICE, "Nc3").await.unwrap();
+    assert_eq!(harness.stored_moves(), vec!["e2e4", "d7d5", "b1c3"]);
+}
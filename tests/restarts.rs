@@ -0,0 +1,80 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{secs, Harness, ALICE, BOB, GUILD_ID};
+use pleco::Player;
+use threadrook::engine::Seat;
+use tokio::time::{sleep, Instant};
+
+#[tokio::test(start_paused = true)]
+async fn restored_matches_carry_on_where_they_were_stored() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+    sleep(Duration::from_secs(2)).await;
+    harness.make_move(ALICE, "e4").await.unwrap();
+    sleep(Duration::from_secs(3)).await;
+    harness.make_move(BOB, "e5").await.unwrap();
+
+    // The bot goes down, and comes back up with only its database.
+    chess_match.task.abort();
+    let harness = harness.restarted();
+    let restored = harness.restore(chess_match.match_id).await;
+    assert!(restored.frontend.recording().resumed);
+
+    let record = &harness.data.database.load_matches().unwrap()[0];
+    assert_eq!(record.progress.player_one_id, ALICE);
+    assert_eq!(record.progress.player_one_colour, Player::White);
+    assert_eq!(record.progress.player_two, Seat::Member(BOB));
+    assert_eq!(record.progress.moves, vec!["e2e4", "e7e5"]);
+
+    let view = harness
+        .data
+        .match_registry
+        .view_board(GUILD_ID, ALICE, restored.match_id)
+        .await
+        .unwrap();
+    assert_eq!(view.colour, Some(Player::White));
+    assert_eq!(
+        view.board.fen(),
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2"
+    );
+
+    // Both members are playing in it again, and the clocks go on from where they were.
+    sleep(Duration::from_secs(1)).await;
+    harness.make_move(ALICE, "Nf3").await.unwrap();
+
+    let records = harness.data.database.load_matches().unwrap();
+    let progress = &records[0].progress;
+    assert_eq!(progress.moves, vec!["e2e4", "e7e5", "g1f3"]);
+    assert_eq!(progress.clock.to_move(), Player::Black);
+    assert_eq!(
+        secs(progress.clock.remaining(Player::White, Instant::now())),
+        297.0
+    );
+    assert_eq!(
+        secs(progress.clock.remaining(Player::Black, Instant::now())),
+        297.0
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn time_spent_on_a_move_is_not_given_back_by_a_restart() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+    harness.make_move(ALICE, "e4").await.unwrap();
+
+    // Bob thinks for 12 seconds, then the bot goes down.
+    sleep(Duration::from_secs(12)).await;
+    chess_match.task.abort();
+    let harness = harness.restarted();
+    let _restored = harness.restore(chess_match.match_id).await;
+
+    // The clock was last stored 10 seconds into the match, so Bob gets back only the 2 seconds since.
+    let records = harness.data.database.load_matches().unwrap();
+    let clock = &records[0].progress.clock;
+    assert_eq!(clock.to_move(), Player::Black);
+    assert_eq!(secs(clock.remaining(Player::Black, Instant::now())), 290.0);
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use common::{secs, Harness, ALICE, BOB};
+use pleco::{Board, Player};
+use threadrook::{
+    clock::TimeControl,
+    commands::create_match::{new_match_engine, start_position},
+    engine::MatchEvent,
+    notation::read_fen,
+    pgn::{write_pgn, PgnHeader},
+};
+use tokio::time::{sleep, Instant};
+
+/// A rook endgame with Black to move on move 40.
+const ROOK_ENDGAME: &str = "8/5k2/8/8/8/8/r4PK1/1R6 b - - 3 40";
+
+#[test]
+fn positions_pleco_accepts_can_still_be_refused() {
+    assert!(read_fen(ROOK_ENDGAME).is_ok());
+    assert!(read_fen("not a fen").is_err());
+    // No black king.
+    assert!(read_fen("8/8/8/8/8/8/8/4K3 w - - 0 1").is_err());
+    // White is in check with Black to move.
+    assert!(read_fen("4k3/8/8/8/8/8/8/r3K3 b - - 0 1").is_err());
+    // The h1 rook has gone, but White could still castle short.
+    assert!(read_fen("4k3/8/8/8/8/8/8/R3K3 w K - 0 1").is_err());
+    assert!(read_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").is_ok());
+    assert!(read_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 0").is_err());
+    // Move counters that pleco would overflow on.
+    assert!(read_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 40000").is_err());
+    assert!(read_fen("4k3/8/8/8/8/8/4R3/4K3 w - - 32767 1").is_err());
+    assert!(read_fen("4k3/8/8/8/8/8/R7/4K3 w - - 32767 1").is_err());
+    assert!(read_fen("4k3/8/8/8/8/8/R7/4K3 w - - 150 1").is_err());
+    assert!(read_fen("4k3/8/8/8/8/8/R7/4K3 b - - 149 32768").is_ok());
+}
+
+#[test]
+fn finished_positions_cant_be_started_from() {
+    // Checkmated, stalemated, and bare kings.
+    assert!(start_position(Some("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1")).is_err());
+    assert!(start_position(Some("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1")).is_err());
+    assert!(start_position(Some("7k/8/6K1/8/8/8/8/8 b - - 0 1")).is_err());
+
+    assert_eq!(
+        start_position(None).unwrap().fen(),
+        Board::start_pos().fen()
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn matches_start_with_the_side_to_move_in_the_fen() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match_from(
+        ALICE,
+        Player::White,
+        TimeControl::default(),
+        read_fen(ROOK_ENDGAME).unwrap(),
+    );
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    harness.make_move(ALICE, "Rb7+").await.unwrap();
+    sleep(Duration::from_secs(4)).await;
+    harness.make_move(BOB, "Ra1").await.unwrap();
+
+    // White's move was sent first, so it waited as a premove for Black's.
+    let sans: Vec<String> = chess_match
+        .events()
+        .into_iter()
+        .filter_map(|event| match event {
+            MatchEvent::MoveApplied { san, .. } => Some(san),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(sans, vec!["40... Ra1", "41. Rb7+"]);
+
+    let records = harness.data.database.load_matches().unwrap();
+    let progress = &records[0].progress;
+    assert_eq!(
+        progress.start_fen.as_deref(),
+        Some(read_fen(ROOK_ENDGAME).unwrap().fen().as_str())
+    );
+    assert_eq!(
+        secs(progress.clock.remaining(Player::Black, Instant::now())),
+        296.0
+    );
+    assert_eq!(
+        secs(progress.clock.remaining(Player::White, Instant::now())),
+        300.0
+    );
+}
+
+#[test]
+fn the_start_position_is_carried_into_the_pgn() {
+    let start = read_fen(ROOK_ENDGAME).unwrap();
+    let engine = new_match_engine(
+        ALICE,
+        Player::White,
+        TimeControl::default(),
+        start.clone(),
+        false,
+    );
+    let pgn = write_pgn(
+        &engine,
+        &PgnHeader {
+            white: "Alice".to_string(),
+            black: "Bob".to_string(),
+            date: UNIX_EPOCH,
+        },
+    );
+
+    assert!(pgn.contains("[SetUp \"1\"]\n"));
+    assert!(pgn.contains(&format!("[FEN \"{}\"]\n", start.fen())));
+}
@@ -0,0 +1,182 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{engine_from_fen, secs, Harness, ALICE, BOB};
+use pleco::{Board, Player};
+use threadrook::engine::MatchEvent;
+use tokio::time::{advance, sleep, Instant};
+
+const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+fn took_back(events: &[MatchEvent]) -> Option<usize> {
+    events.iter().find_map(|event| match event {
+        MatchEvent::TakebackAccepted { moves, .. } => Some(*moves),
+        _ => None,
+    })
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_takeback_before_the_reply_undoes_one_move_and_restores_the_clocks() {
+    let mut engine = engine_from_fen(START);
+    advance(Duration::from_secs(10)).await;
+    engine.make_move(ALICE, "e4", Instant::now());
+    advance(Duration::from_secs(5)).await;
+
+    engine.request_takeback(ALICE, Instant::now());
+    advance(Duration::from_secs(2)).await;
+    let events = engine.accept_takeback(BOB, Instant::now());
+
+    assert_eq!(took_back(&events), Some(1));
+    assert_eq!(engine.board().fen(), Board::start_pos().fen());
+    assert!(engine.moves().is_empty());
+    assert!(engine.move_clocks().is_empty());
+    // White's turn starts again with the time they had before playing e4.
+    assert_eq!(engine.acting_colour(), Player::White);
+    assert_eq!(
+        secs(engine.get_colour_timeleft(Player::White, Instant::now())),
+        300.0
+    );
+    assert_eq!(
+        secs(engine.get_colour_timeleft(Player::Black, Instant::now())),
+        300.0
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_takeback_after_the_reply_undoes_both_moves() {
+    let mut engine = engine_from_fen(START);
+    for (user, chess_move) in [(ALICE, "e4"), (BOB, "e5"), (ALICE, "Nf3"), (BOB, "Nc6")] {
+        advance(Duration::from_secs(3)).await;
+        engine.make_move(user, chess_move, Instant::now());
+    }
+
+    engine.request_takeback(BOB, Instant::now());
+    engine.request_takeback(ALICE, Instant::now());
+    // Asking for a takeback of your own while your opponent's is open accepts theirs.
+    assert_eq!(engine.moves(), ["e2e4", "e7e5", "g1f3"]);
+
+    engine.make_move(BOB, "Nc6", Instant::now());
+    // It is White's turn, so taking back White's last move takes back Black's reply too.
+    engine.request_takeback(ALICE, Instant::now());
+    let events = engine.accept_takeback(BOB, Instant::now());
+
+    assert_eq!(took_back(&events), Some(2));
+    assert_eq!(engine.moves(), ["e2e4", "e7e5"]);
+    assert_eq!(engine.acting_colour(), Player::White);
+    assert_eq!(
+        secs(engine.get_colour_timeleft(Player::White, Instant::now())),
+        297.0
+    );
+    assert_eq!(
+        secs(engine.get_colour_timeleft(Player::Black, Instant::now())),
+        297.0
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn takebacks_need_a_move_and_an_open_request() {
+    let mut engine = engine_from_fen(START);
+
+    assert_eq!(
+        engine.request_takeback(ALICE, Instant::now()),
+        vec![MatchEvent::NothingToTakeBack(ALICE)]
+    );
+    assert_eq!(
+        engine.accept_takeback(BOB, Instant::now()),
+        vec![MatchEvent::NoTakebackRequest(BOB)]
+    );
+
+    engine.make_move(ALICE, "e4", Instant::now());
+    engine.request_takeback(ALICE, Instant::now());
+    // Only the opponent can answer.
+    assert_eq!(
+        engine.accept_takeback(ALICE, Instant::now()),
+        vec![MatchEvent::NoTakebackRequest(ALICE)]
+    );
+
+    // Moving instead of answering withdraws the request.
+    engine.make_move(BOB, "e5", Instant::now());
+    assert_eq!(engine.takeback_request(), None);
+    assert_eq!(
+        engine.accept_takeback(BOB, Instant::now()),
+        vec![MatchEvent::NoTakebackRequest(BOB)]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn declined_takebacks_leave_the_game_alone() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    harness.make_move(ALICE, "e4").await.unwrap();
+    harness.request_takeback(ALICE).await.unwrap();
+    assert!(harness.answer_takeback(BOB, 1, false).await.unwrap());
+    sleep(Duration::from_secs(1)).await;
+
+    assert!(chess_match.events().iter().any(
+        |event| matches!(event, MatchEvent::TakebackDeclined(player) if player.user_id() == Some(BOB))
+    ));
+    let records = harness.data.database.load_matches().unwrap();
+    assert_eq!(records[0].progress.moves, vec!["e2e4"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn accepted_takebacks_are_stored() {
+    let mut harness = Harness::new();
+    let _chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    harness.make_move(ALICE, "e4").await.unwrap();
+    harness.make_move(BOB, "e5").await.unwrap();
+    harness.request_takeback(BOB).await.unwrap();
+    assert!(harness.answer_takeback(ALICE, 2, true).await.unwrap());
+
+    let records = harness.data.database.load_matches().unwrap();
+    assert_eq!(records[0].progress.moves, vec!["e2e4"]);
+    assert_eq!(records[0].progress.move_clocks.len(), 1);
+    assert_eq!(records[0].progress.clock.to_move(), Player::Black);
+}
+
+#[tokio::test(start_paused = true)]
+async fn answers_to_a_request_the_game_has_moved_past_are_rejected() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    harness.make_move(ALICE, "e4").await.unwrap();
+    harness.request_takeback(ALICE).await.unwrap();
+    harness.make_move(BOB, "e5").await.unwrap();
+    harness.make_move(ALICE, "Nf3").await.unwrap();
+    harness.request_takeback(ALICE).await.unwrap();
+
+    // Bob clicks the buttons of the first request, which was withdrawn when he moved.
+    assert!(!harness.answer_takeback(BOB, 1, true).await.unwrap());
+    assert!(took_back(&chess_match.events()).is_none());
+
+    assert!(harness.answer_takeback(BOB, 3, true).await.unwrap());
+    assert_eq!(took_back(&chess_match.events()), Some(1));
+}
+
+#[tokio::test(start_paused = true)]
+async fn rated_matches_do_not_allow_takebacks() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_rated_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    harness.make_move(ALICE, "e4").await.unwrap();
+    harness.request_takeback(ALICE).await.unwrap();
+
+    assert!(chess_match
+        .events()
+        .contains(&MatchEvent::TakebacksNotAllowed(ALICE)));
+    assert!(!chess_match
+        .events()
+        .iter()
+        .any(|event| matches!(event, MatchEvent::TakebackRequested { .. })));
+
+    // A restart keeps the match rated.
+    let records = harness.data.database.load_matches().unwrap();
+    assert!(records[0].progress.rated);
+}
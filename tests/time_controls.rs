@@ -0,0 +1,259 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{ending_of, engine_from_fen, secs, settle, Harness, ALICE, BOB};
+use pleco::Player;
+use threadrook::{
+    clock::{ClockMode, TimeControl},
+    engine::{GameEndReason, GameResult},
+    frontend::format_clock,
+};
+use tokio::time::{sleep, Instant};
+
+#[test]
+fn time_controls_are_written_as_minutes_plus_increment() {
+    assert_eq!(TimeControl::new(180, 2).to_string(), "3+2");
+    assert_eq!(TimeControl::new(30, 0).to_string(), "0.5+0");
+    assert_eq!(TimeControl::default().to_string(), "5+0");
+}
+
+#[test]
+fn time_controls_are_categorised_by_the_length_of_a_forty_move_game() {
+    assert_eq!(TimeControl::new(60, 0).category(), "Bullet");
+    assert_eq!(TimeControl::new(120, 2).category(), "Blitz");
+    assert_eq!(TimeControl::new(600, 5).category(), "Rapid");
+    assert_eq!(TimeControl::new(1800, 0).category(), "Classical");
+}
+
+#[tokio::test(start_paused = true)]
+async fn increment_is_added_after_each_accepted_move() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_timed_match(ALICE, Player::White, TimeControl::new(180, 2));
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    sleep(Duration::from_secs(10)).await;
+    harness.make_move(ALICE, "e2e4").await.unwrap();
+    harness.make_move(ALICE, "d2d4").await.unwrap();
+    settle().await;
+
+    let records = harness.data.database.load_matches().unwrap();
+    assert_eq!(records[0].match_id, chess_match.match_id);
+    assert_eq!(
+        records[0].progress.clock.time_control(),
+        TimeControl::new(180, 2)
+    );
+    assert_eq!(
+        secs(
+            records[0]
+                .progress
+                .clock
+                .remaining(Player::White, Instant::now())
+        ),
+        172.0
+    );
+    assert_eq!(
+        secs(
+            records[0]
+                .progress
+                .clock
+                .remaining(Player::Black, Instant::now())
+        ),
+        180.0
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn bullet_flag_falls_after_one_minute() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_timed_match(ALICE, Player::White, TimeControl::new(60, 0));
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    sleep(Duration::from_millis(59_500)).await;
+    assert_eq!(chess_match.ending(), None);
+
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(
+        chess_match.ending(),
+        Some((
+            GameResult::Winner(Player::Black),
+            GameEndReason::Timeout(Player::White)
+        ))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn us_delay_only_runs_the_clock_once_the_delay_has_passed() {
+    let mut harness = Harness::new();
+    let time_control = TimeControl::new(180, 5).with_mode(ClockMode::UsDelay);
+    let _chess_match = harness.create_timed_match(ALICE, Player::White, time_control);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    sleep(Duration::from_secs(3)).await;
+    harness.make_move(ALICE, "e2e4").await.unwrap();
+    sleep(Duration::from_secs(8)).await;
+    harness.make_move(BOB, "e7e5").await.unwrap();
+
+    let clock = &harness.data.database.load_matches().unwrap()[0]
+        .progress
+        .clock;
+    assert_eq!(clock.time_control(), time_control);
+    assert_eq!(secs(clock.remaining(Player::White, Instant::now())), 180.0);
+    assert_eq!(secs(clock.remaining(Player::Black, Instant::now())), 177.0);
+    assert_eq!(secs(clock.turn_time(Instant::now())), 0.0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn bronstein_delay_gives_back_the_time_used_up_to_the_delay() {
+    let mut harness = Harness::new();
+    let time_control = TimeControl::new(180, 5).with_mode(ClockMode::Bronstein);
+    let _chess_match = harness.create_timed_match(ALICE, Player::White, time_control);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    sleep(Duration::from_secs(3)).await;
+    harness.make_move(ALICE, "e2e4").await.unwrap();
+    sleep(Duration::from_secs(8)).await;
+    harness.make_move(BOB, "e7e5").await.unwrap();
+
+    let clock = &harness.data.database.load_matches().unwrap()[0]
+        .progress
+        .clock;
+    assert_eq!(secs(clock.remaining(Player::White, Instant::now())), 180.0);
+    assert_eq!(secs(clock.remaining(Player::Black, Instant::now())), 177.0);
+}
+
+#[test]
+fn delays_are_written_after_the_base_time() {
+    let time_control = TimeControl::new(180, 2);
+
+    assert_eq!(
+        time_control.with_mode(ClockMode::UsDelay).to_string(),
+        "3 d2"
+    );
+    assert_eq!(
+        time_control.with_mode(ClockMode::Bronstein).to_string(),
+        "3 b2"
+    );
+}
+
+#[test]
+fn clocks_show_tenths_only_in_the_last_ten_seconds() {
+    assert_eq!(format_clock(Duration::from_secs(300)), "5:00");
+    assert_eq!(format_clock(Duration::from_millis(10_400)), "0:10");
+    assert_eq!(format_clock(Duration::from_millis(9_970)), "0:10");
+    assert_eq!(format_clock(Duration::from_millis(9_940)), "0:09.9");
+    assert_eq!(format_clock(Duration::from_millis(420)), "0:00.4");
+    assert_eq!(format_clock(Duration::ZERO), "0:00.0");
+}
+
+#[tokio::test(start_paused = true)]
+async fn clocks_keep_fractions_of_a_second() {
+    let mut harness = Harness::new();
+    let _chess_match = harness.create_match(ALICE, Player::White);
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    sleep(Duration::from_millis(2_500)).await;
+    harness.make_move(ALICE, "e2e4").await.unwrap();
+
+    let clock = &harness.data.database.load_matches().unwrap()[0]
+        .progress
+        .clock;
+    assert_eq!(secs(clock.remaining(Player::White, Instant::now())), 297.5);
+}
+
+#[tokio::test(start_paused = true)]
+async fn flag_falls_between_clock_refreshes() {
+    let mut harness = Harness::new();
+    let chess_match = harness.create_timed_match(ALICE, Player::White, TimeControl::new(60, 0));
+    harness.join_match(ALICE, BOB).await.unwrap();
+
+    sleep(Duration::from_millis(500)).await;
+    harness.make_move(ALICE, "e2e4").await.unwrap();
+
+    sleep(Duration::from_millis(59_900)).await;
+    assert_eq!(chess_match.ending(), None);
+
+    sleep(Duration::from_millis(200)).await;
+    assert_eq!(
+        chess_match.ending(),
+        Some((
+            GameResult::Winner(Player::White),
+            GameEndReason::Timeout(Player::Black)
+        ))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn flagging_against_a_lone_king_or_minor_piece_that_cannot_mate_is_a_draw() {
+    let flag_fall = Instant::now() + Duration::from_secs(301);
+
+    let mut lone_king = engine_from_fen("8/8/8/4k3/8/8/4K3/4R3 w - - 0 1");
+    assert_eq!(
+        ending_of(&lone_king.expire(flag_fall)),
+        Some((
+            GameResult::Draw,
+            GameEndReason::TimeoutVsInsufficientMaterial(Player::White)
+        ))
+    );
+
+    let mut knight = engine_from_fen("8/8/4n3/4k3/8/8/4K3/8 w - - 0 1");
+    assert_eq!(
+        ending_of(&knight.expire(flag_fall)),
+        Some((
+            GameResult::Draw,
+            GameEndReason::TimeoutVsInsufficientMaterial(Player::White)
+        ))
+    );
+
+    // Both bishops stand on light squares, so White's can never block its own king's escape.
+    let mut bishops = engine_from_fen("8/8/4b3/4k3/8/8/4K3/5B2 w - - 0 1");
+    assert_eq!(
+        ending_of(&bishops.expire(flag_fall)),
+        Some((
+            GameResult::Draw,
+            GameEndReason::TimeoutVsInsufficientMaterial(Player::White)
+        ))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn flagging_against_mating_material_loses() {
+    let flag_fall = Instant::now() + Duration::from_secs(301);
+
+    // The rook can box its own king in, so a lone knight could still mate.
+    let mut knight = engine_from_fen("8/8/4n3/4k3/8/8/4K3/4R3 w - - 0 1");
+    assert_eq!(
+        ending_of(&knight.expire(flag_fall)),
+        Some((
+            GameResult::Winner(Player::Black),
+            GameEndReason::Timeout(Player::White)
+        ))
+    );
+
+    let mut bishops = engine_from_fen("8/8/4b3/4k3/8/8/4K3/4B3 w - - 0 1");
+    assert_eq!(
+        ending_of(&bishops.expire(flag_fall)),
+        Some((
+            GameResult::Winner(Player::Black),
+            GameEndReason::Timeout(Player::White)
+        ))
+    );
+
+    let mut rook = engine_from_fen("8/8/8/4k3/8/8/4K3/4R3 b - - 0 1");
+    assert_eq!(
+        ending_of(&rook.expire(flag_fall)),
+        Some((
+            GameResult::Winner(Player::White),
+            GameEndReason::Timeout(Player::Black)
+        ))
+    );
+
+    let mut pawn = engine_from_fen("8/8/4p3/4k3/8/8/4K3/8 w - - 0 1");
+    assert_eq!(
+        ending_of(&pawn.expire(flag_fall)),
+        Some((
+            GameResult::Winner(Player::Black),
+            GameEndReason::Timeout(Player::White)
+        ))
+    );
+}